@@ -0,0 +1,271 @@
+use bevy::prelude::*;
+use cgmath::Rad;
+use serde::Deserialize;
+
+use crate::settings::load_asset_file;
+
+use std::collections::HashMap;
+
+static SPRITE_METADATA_FILE: &str = "sprites/people.ron";
+static BUNDLED_SPRITE_METADATA: &str = include_str!("../assets/sprites/people.ron");
+
+/// Distance a character has to move in a frame to count as walking
+static WALK_THRESHOLD: f32 = 0.1;
+
+/// Facing, counter-clockwise from east
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    East,
+    NorthEast,
+    North,
+    NorthWest,
+    West,
+    SouthWest,
+    South,
+    SouthEast,
+}
+
+impl Direction {
+    pub fn from_angle(angle: Rad<f32>) -> Self {
+        let degrees = angle.0.to_degrees().rem_euclid(360.0);
+
+        match ((degrees + 22.5) / 45.0) as u32 % 8 {
+            0 => Direction::East,
+            1 => Direction::NorthEast,
+            2 => Direction::North,
+            3 => Direction::NorthWest,
+            4 => Direction::West,
+            5 => Direction::SouthWest,
+            6 => Direction::South,
+            _ => Direction::SouthEast,
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+#[derive(Deserialize)]
+struct SheetMetadata {
+    texture: String,
+    tile_size: (f32, f32),
+    columns: u32,
+    rows: u32,
+}
+
+#[derive(Deserialize)]
+struct AnimationMetadata {
+    /// Column offsets from the character's start point
+    frames: Vec<u32>,
+    frame_seconds: f32,
+    /// Row offset to always use instead of the one for the current facing
+    #[serde(default)]
+    row: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct CharacterMetadata {
+    sheet: String,
+    /// Top left cell of this character's block
+    start: (u32, u32),
+    /// Size the character is drawn at in the world
+    size: (f32, f32),
+    #[serde(default = "CharacterMetadata::default_tint")]
+    tint: (f32, f32, f32),
+    /// Row offset from `start` for each `Direction`
+    direction_rows: Vec<u32>,
+    animations: HashMap<String, AnimationMetadata>,
+}
+
+impl CharacterMetadata {
+    fn default_tint() -> (f32, f32, f32) {
+        (1.0, 1.0, 1.0)
+    }
+}
+
+#[derive(Deserialize)]
+struct SpriteMetadataFile {
+    sheets: HashMap<String, SheetMetadata>,
+    characters: HashMap<String, CharacterMetadata>,
+}
+
+struct LoadedSheet {
+    atlas: Handle<TextureAtlas>,
+    tile_size: Vec2,
+    columns: u32,
+}
+
+/// Every sprite sheet and the characters drawn from them
+pub struct SpriteSheets {
+    sheets: HashMap<String, LoadedSheet>,
+    characters: HashMap<String, CharacterMetadata>,
+}
+
+impl SpriteSheets {
+    fn character(&self, name: &str) -> &CharacterMetadata {
+        self.characters
+            .get(name)
+            .unwrap_or_else(|| panic!("No sprite metadata for character '{}'", name))
+    }
+
+    fn sheet(&self, character: &CharacterMetadata) -> &LoadedSheet {
+        self.sheets
+            .get(&character.sheet)
+            .unwrap_or_else(|| panic!("No sprite sheet named '{}'", character.sheet))
+    }
+
+    fn index(&self, character: &CharacterMetadata, column: u32, row: u32) -> u32 {
+        let sheet = self.sheet(character);
+        (character.start.1 + row) * sheet.columns + character.start.0 + column
+    }
+
+    /// A sprite sheet bundle showing `name` standing still and facing east
+    pub fn bundle(&self, name: &str, translation: Vec3) -> SpriteSheetBundle {
+        let character = self.character(name);
+        let sheet = self.sheet(character);
+        let first_frame = character
+            .animations
+            .get("idle")
+            .and_then(|idle| idle.frames.first())
+            .copied()
+            .unwrap_or(0);
+        let (r, g, b) = character.tint;
+
+        let mut transform = Transform::from_translation(translation);
+        transform.scale = (Vec2::new(character.size.0, character.size.1) / sheet.tile_size).extend(1.0);
+
+        SpriteSheetBundle {
+            texture_atlas: sheet.atlas.clone(),
+            sprite: TextureAtlasSprite {
+                index: self.index(character, first_frame, character.direction_rows[Direction::East.index()]),
+                color: Color::rgb(r, g, b),
+                ..Default::default()
+            },
+            transform,
+            ..Default::default()
+        }
+    }
+}
+
+/// Which character an entity is drawn as and where it is in its animation
+pub struct CharacterAnimation {
+    character: String,
+    /// Clip played through once instead of walking or idling
+    playing: Option<String>,
+    frame: usize,
+    frame_elapsed: f32,
+    last_location: Option<Vec2>,
+    moving: bool,
+}
+
+impl CharacterAnimation {
+    pub fn new(character: &str) -> Self {
+        CharacterAnimation {
+            character: character.to_string(),
+            playing: None,
+            frame: 0,
+            frame_elapsed: 0.0,
+            last_location: None,
+            moving: false,
+        }
+    }
+
+    /// Whether the character moved last frame
+    pub fn is_moving(&self) -> bool {
+        self.moving
+    }
+
+    /// Plays `clip` and holds its last frame
+    pub fn play_once(&mut self, clip: &str) {
+        self.playing = Some(clip.to_string());
+        self.frame = 0;
+        self.frame_elapsed = 0.0;
+    }
+}
+
+pub fn animate_sprites(
+    time: Res<Time>,
+    sprite_sheets: Res<SpriteSheets>,
+    mut query: Query<(&Rad<f32>, &mut CharacterAnimation, &mut TextureAtlasSprite, &Transform)>,
+) {
+    for (radian, mut animation, mut sprite, trans) in query.iter_mut() {
+        let location = trans.translation.truncate();
+        let moving = animation
+            .last_location
+            .map(|last| (location - last).length() > WALK_THRESHOLD)
+            .unwrap_or(false);
+        animation.last_location = Some(location);
+        animation.moving = moving;
+
+        let character = sprite_sheets.character(&animation.character);
+        let clip_name = match (&animation.playing, moving) {
+            (Some(playing), _) => playing.as_str(),
+            (None, true) => "walk",
+            (None, false) => "idle",
+        };
+        let clip = match character.animations.get(clip_name) {
+            Some(clip) if !clip.frames.is_empty() => clip,
+            _ => continue,
+        };
+        let looping = animation.playing.is_none();
+
+        animation.frame_elapsed += time.delta_seconds();
+        if animation.frame_elapsed >= clip.frame_seconds {
+            animation.frame_elapsed -= clip.frame_seconds;
+            animation.frame += 1;
+        }
+        animation.frame = match looping {
+            true => animation.frame % clip.frames.len(),
+            false => animation.frame.min(clip.frames.len() - 1),
+        };
+
+        let row = clip
+            .row
+            .unwrap_or(character.direction_rows[Direction::from_angle(*radian).index()]);
+        sprite.index = sprite_sheets.index(character, clip.frames[animation.frame], row);
+    }
+}
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn load_sprite_sheets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let metadata: SpriteMetadataFile = load_asset_file(SPRITE_METADATA_FILE, BUNDLED_SPRITE_METADATA);
+
+    for (name, character) in metadata.characters.iter() {
+        if character.direction_rows.len() != 8 {
+            panic!("Character '{}' needs a row for each of the 8 directions", name);
+        }
+    }
+
+    let sheets = metadata
+        .sheets
+        .into_iter()
+        .map(|(name, sheet)| {
+            let tile_size = Vec2::new(sheet.tile_size.0, sheet.tile_size.1);
+            let atlas = TextureAtlas::from_grid(
+                asset_server.load(sheet.texture.as_str()),
+                tile_size,
+                sheet.columns as usize,
+                sheet.rows as usize,
+            );
+
+            let loaded = LoadedSheet {
+                atlas: texture_atlases.add(atlas),
+                tile_size,
+                columns: sheet.columns,
+            };
+            (name, loaded)
+        })
+        .collect();
+
+    commands.insert_resource(SpriteSheets {
+        sheets,
+        characters: metadata.characters,
+    });
+}
+// -----------------------------------
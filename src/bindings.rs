@@ -0,0 +1,183 @@
+use bevy::input::gamepad::GamepadButton;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::gamepad::GamepadInput;
+use crate::net::RemoteInput;
+use crate::settings::{load_data_file, save_data_file};
+
+use std::collections::HashMap;
+
+static BINDINGS_FILE: &str = "bindings.ron";
+
+/// Something a player can do, read through `PlayerActions` instead of raw keys
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Sprint,
+    Dash,
+    Fire,
+    Reload,
+    SwitchWeapon,
+    ThrowGrenade,
+    Melee,
+}
+
+impl Action {
+    /// Every action, in the order the rebinding menu lists them
+    pub const ALL: [Action; 11] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Sprint,
+        Action::Dash,
+        Action::Fire,
+        Action::Reload,
+        Action::SwitchWeapon,
+        Action::ThrowGrenade,
+        Action::Melee,
+    ];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::Sprint => "Sprint",
+            Action::Dash => "Dash",
+            Action::Fire => "Fire",
+            Action::Reload => "Reload",
+            Action::SwitchWeapon => "Switch weapon",
+            Action::ThrowGrenade => "Throw grenade",
+            Action::Melee => "Melee",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    fn pressed(&self, keys: &Input<KeyCode>, mouse: &Input<MouseButton>) -> bool {
+        match self {
+            Binding::Key(key) => keys.pressed(*key),
+            Binding::Mouse(button) => mouse.pressed(*button),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("Mouse {:?}", button),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Bindings(HashMap<Action, Binding>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let defaults = [
+            (Action::MoveUp, Binding::Key(KeyCode::W)),
+            (Action::MoveDown, Binding::Key(KeyCode::S)),
+            (Action::MoveLeft, Binding::Key(KeyCode::A)),
+            (Action::MoveRight, Binding::Key(KeyCode::D)),
+            (Action::Sprint, Binding::Key(KeyCode::LShift)),
+            (Action::Dash, Binding::Key(KeyCode::Space)),
+            (Action::Fire, Binding::Mouse(MouseButton::Left)),
+            (Action::Reload, Binding::Key(KeyCode::R)),
+            (Action::SwitchWeapon, Binding::Key(KeyCode::Q)),
+            (Action::ThrowGrenade, Binding::Key(KeyCode::G)),
+            (Action::Melee, Binding::Key(KeyCode::F)),
+        ];
+
+        Bindings(defaults.iter().cloned().collect())
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action) -> Option<Binding> {
+        self.0.get(&action).copied()
+    }
+
+    /// Binds `action`, swapping with whichever action already used the binding
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let previous = self.0.get(&action).copied();
+        let clashing = self.0.iter().find(|(_, bound)| **bound == binding).map(|(other, _)| *other);
+
+        if let (Some(other), Some(previous)) = (clashing, previous) {
+            self.0.insert(other, previous);
+        }
+        self.0.insert(action, binding);
+        save_data_file(BINDINGS_FILE, self);
+    }
+}
+
+/// What one player's bindings and gamepad are asking for this frame
+#[derive(Default)]
+pub struct PlayerActions(Input<Action>);
+
+impl PlayerActions {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.0.pressed(action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.0.just_pressed(action)
+    }
+}
+
+/// Mirrors the bound keys and buttons, each player's gamepad, or what a remote player sent, into their `PlayerActions`
+pub fn update_actions(
+    bindings: Res<Bindings>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut player_query: Query<(&GamepadInput, Option<&RemoteInput>, &mut PlayerActions)>,
+) {
+    for (gamepad, remote, mut actions) in player_query.iter_mut() {
+        let actions = &mut actions.0;
+        actions.update();
+
+        for action in Action::ALL.iter() {
+            let bound_down = gamepad.uses_keyboard_mouse()
+                && bindings
+                    .get(*action)
+                    .map(|binding| binding.pressed(&keys, &mouse))
+                    .unwrap_or(false);
+            let down = match remote {
+                Some(remote) => remote.pressed(*action),
+                None => bound_down || gamepad.action_pressed(*action, &gamepad_buttons),
+            };
+
+            match (down, actions.pressed(*action)) {
+                (true, false) => actions.press(*action),
+                (false, true) => actions.release(*action),
+                _ => {}
+            }
+        }
+    }
+}
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn load_bindings(mut commands: Commands) {
+    let mut bindings = load_data_file::<Bindings>(BINDINGS_FILE);
+
+    // Actions added since the file was saved keep their default
+    for (action, binding) in Bindings::default().0 {
+        bindings.0.entry(action).or_insert(binding);
+    }
+
+    commands.insert_resource(bindings);
+}
+// -----------------------------------
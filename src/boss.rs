@@ -0,0 +1,328 @@
+use bevy::prelude::*;
+use rand::{self, Rng};
+
+use crate::animation::SpriteSheets;
+use crate::coop::Downed;
+use crate::events::{PlayerHurt, ZomKilled};
+use crate::particles::{EmitParticles, ParticlePreset};
+use crate::{Health, Materials, Player, Velocity, ZomType};
+
+static CHARGE_SPEED: f32 = 7.0;
+static CHARGE_SECONDS: f32 = 0.6;
+static CHARGE_DAMAGE: f32 = 25.0;
+static SUMMON_COUNT: u32 = 4;
+static SUMMON_DISTANCE: f32 = 60.0;
+static SLAM_WINDUP_SECONDS: f32 = 0.8;
+static SLAM_RADIUS: f32 = 120.0;
+static SLAM_DAMAGE: f32 = 20.0;
+static REWARD_SIZE: f32 = 16.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BossPhase {
+    Charge,
+    Summon,
+    Slam,
+}
+
+impl BossPhase {
+    fn for_health(health: &Health) -> Self {
+        let fraction = health.fraction();
+
+        if fraction > 0.66 {
+            BossPhase::Charge
+        } else if fraction > 0.33 {
+            BossPhase::Summon
+        } else {
+            BossPhase::Slam
+        }
+    }
+
+    fn attack_interval(&self) -> f32 {
+        match self {
+            BossPhase::Charge => 3.0,
+            BossPhase::Summon => 5.0,
+            BossPhase::Slam => 3.5,
+        }
+    }
+}
+
+struct Charge {
+    direction: Vec2,
+    timer: Timer,
+    /// Players already run over, each is only hurt once per charge
+    hit: Vec<Entity>,
+}
+
+pub struct Boss {
+    phase: BossPhase,
+    attack_timer: Timer,
+    charge: Option<Charge>,
+    slam_windup: Option<Timer>,
+}
+
+impl Boss {
+    fn new() -> Self {
+        Boss {
+            phase: BossPhase::Charge,
+            attack_timer: Timer::from_seconds(BossPhase::Charge.attack_interval(), true),
+            charge: None,
+            slam_windup: None,
+        }
+    }
+}
+
+/// Telegraphs the area a slam is about to hit
+pub struct SlamMarker(Timer);
+
+/// Dropped where a boss dies, restores the player when walked over
+pub struct BossReward;
+
+pub struct BossHealthFrame;
+
+pub struct BossHealthBar;
+
+pub fn spawn_boss(
+    mut commands: Commands,
+    sprite_sheets: Res<SpriteSheets>,
+    windows: Res<Windows>,
+    mut wave: ResMut<crate::wave::Wave>,
+) {
+    if !wave.boss_pending {
+        return;
+    }
+
+    let mut random = rand::thread_rng();
+    let translation = crate::edge_spawn_location(&mut random, windows.get_primary().unwrap());
+
+    let boss = crate::spawn_zom_at(&mut commands, &sprite_sheets, ZomType::Boss, translation);
+    commands.entity(boss).insert(Boss::new());
+
+    wave.boss_pending = false;
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn boss_attack(
+    mut commands: Commands,
+    sprite_sheets: Res<SpriteSheets>,
+    materials: Res<Materials>,
+    time: Res<Time>,
+    mut particle_writer: EventWriter<EmitParticles>,
+    mut hurt_writer: EventWriter<PlayerHurt>,
+    mut boss_query: Query<(&mut Boss, &Health, &mut Transform), Without<Player>>,
+    player_query: Query<(&Transform, Entity), (With<Player>, Without<Boss>, Without<Downed>)>,
+) {
+    let players: Vec<(Vec2, Entity)> = player_query
+        .iter()
+        .map(|(trans, entity)| (trans.translation.truncate(), entity))
+        .collect();
+
+    for (mut boss, health, mut trans) in boss_query.iter_mut() {
+        let boss = &mut *boss;
+        let boss_location = trans.translation.truncate();
+        let dist_to = |location: &Vec2| Velocity::between_transforms(&boss_location, location).magnitude();
+        let nearest = players
+            .iter()
+            .min_by(|a, b| dist_to(&a.0).partial_cmp(&dist_to(&b.0)).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(location, _)| *location);
+
+        // Switch attack pattern once health drops past a threshold
+        let phase = BossPhase::for_health(health);
+        if phase != boss.phase {
+            boss.phase = phase;
+            boss.attack_timer = Timer::from_seconds(phase.attack_interval(), true);
+        }
+
+        if let Some(charge) = &mut boss.charge {
+            trans.translation += (charge.direction * CHARGE_SPEED).extend(0.0);
+
+            for (location, player) in players.iter() {
+                if !charge.hit.contains(player) && dist_to(location) < crate::BOSS_SIZE {
+                    hurt_writer.send(PlayerHurt {
+                        player: *player,
+                        amount: CHARGE_DAMAGE,
+                        source: boss_location,
+                    });
+                    charge.hit.push(*player);
+                }
+            }
+
+            if charge.timer.tick(time.delta()).finished() {
+                boss.charge = None;
+            }
+            continue;
+        }
+
+        if let Some(windup) = &mut boss.slam_windup {
+            if windup.tick(time.delta()).finished() {
+                for (location, player) in players.iter() {
+                    if dist_to(location) < SLAM_RADIUS {
+                        hurt_writer.send(PlayerHurt {
+                            player: *player,
+                            amount: SLAM_DAMAGE,
+                            source: boss_location,
+                        });
+                    }
+                }
+                particle_writer.send(EmitParticles {
+                    preset: ParticlePreset::Explosion,
+                    location: boss_location,
+                    direction: 0.0,
+                });
+                boss.slam_windup = None;
+            }
+            continue;
+        }
+
+        if !boss.attack_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+
+        // Attacks go for the nearest player still standing
+        let player_location = match nearest {
+            Some(location) => location,
+            None => continue,
+        };
+
+        match boss.phase {
+            BossPhase::Charge => {
+                let (x, y) = Velocity::between_transforms(&boss_location, &player_location).unit_vec();
+                boss.charge = Some(Charge {
+                    direction: Vec2::new(x, y),
+                    timer: Timer::from_seconds(CHARGE_SECONDS, false),
+                    hit: Vec::new(),
+                });
+            }
+            BossPhase::Summon => {
+                let mut random = rand::thread_rng();
+                for _index in 0..SUMMON_COUNT {
+                    let angle = random.gen_range(0.0..(std::f32::consts::PI * 2.0));
+                    let offset = Vec3::new(angle.cos(), angle.sin(), 0.0) * SUMMON_DISTANCE;
+
+                    crate::spawn_zom_at(
+                        &mut commands,
+                        &sprite_sheets,
+                        ZomType::Default,
+                        trans.translation + offset,
+                    );
+                }
+            }
+            BossPhase::Slam => {
+                boss.slam_windup = Some(Timer::from_seconds(SLAM_WINDUP_SECONDS, false));
+
+                commands
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite::new(Vec2::new(SLAM_RADIUS * 2.0, SLAM_RADIUS * 2.0)),
+                        material: materials.slam_marker.clone(),
+                        transform: Transform::from_xyz(boss_location.x, boss_location.y, 0.05),
+                        ..Default::default()
+                    })
+                    .insert(SlamMarker(Timer::from_seconds(SLAM_WINDUP_SECONDS, false)));
+            }
+        }
+    }
+}
+
+pub fn fade_slam_markers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut marker_query: Query<(&mut SlamMarker, Entity)>,
+) {
+    for (mut marker, entity) in marker_query.iter_mut() {
+        if marker.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Leaves a reward where each boss died
+pub fn drop_rewards(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    mut killed_reader: EventReader<ZomKilled>,
+) {
+    for killed in killed_reader.iter().filter(|killed| killed.zom_type == ZomType::Boss) {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite::new(Vec2::new(REWARD_SIZE, REWARD_SIZE)),
+                material: materials.reward.clone(),
+                transform: Transform::from_xyz(killed.location.x, killed.location.y, 0.05),
+                ..Default::default()
+            })
+            .insert(BossReward);
+    }
+}
+
+pub fn collect_reward(
+    mut commands: Commands,
+    reward_query: Query<(&Transform, Entity), With<BossReward>>,
+    mut player_query: Query<(&Transform, &mut Health), (With<Player>, Without<Downed>)>,
+) {
+    for (player_trans, mut health) in player_query.iter_mut() {
+        for (reward_trans, entity) in reward_query.iter() {
+            let dist = Velocity::between_transforms(
+                &reward_trans.translation.truncate(),
+                &player_trans.translation.truncate(),
+            )
+            .magnitude();
+
+            if dist < REWARD_SIZE * 2.0 {
+                health.restore();
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn load_boss_hud(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(10.0),
+                    left: Val::Percent(25.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(50.0), Val::Px(16.0)),
+                padding: Rect::all(Val::Px(2.0)),
+                ..Default::default()
+            },
+            material: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            ..Default::default()
+        })
+        .insert(BossHealthFrame)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        ..Default::default()
+                    },
+                    material: materials.add(Color::CRIMSON.into()),
+                    ..Default::default()
+                })
+                .insert(BossHealthBar);
+        });
+}
+// -----------------------------------
+
+pub fn update_boss_hud(
+    boss_query: Query<&Health, With<Boss>>,
+    mut frame_query: Query<&mut Style, (With<BossHealthFrame>, Without<BossHealthBar>)>,
+    mut bar_query: Query<&mut Style, (With<BossHealthBar>, Without<BossHealthFrame>)>,
+) {
+    if let (Ok(mut frame_style), Ok(mut bar_style)) = (frame_query.single_mut(), bar_query.single_mut()) {
+        match boss_query.iter().next() {
+            Some(health) => {
+                frame_style.display = Display::Flex;
+                bar_style.size.width = Val::Percent(health.fraction() * 100.0);
+            }
+            None => {
+                frame_style.display = Display::None;
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use bevy::input::gamepad::{GamepadButton, GamepadButtonType};
+use bevy::prelude::*;
+
+use crate::animation::SpriteSheets;
+use crate::gamepad::{ConnectedGamepads, GamepadInput};
+use crate::{Health, Player};
+
+use std::f32::consts::FRAC_PI_2;
+
+pub static MAX_PLAYERS: usize = 4;
+/// How close a teammate has to stand to revive someone, in pixels
+static REVIVE_RADIUS: f32 = 40.0;
+static REVIVE_SECONDS: f32 = 3.0;
+/// Fraction of max health a revived player gets back
+static REVIVE_HEALTH: f32 = 0.3;
+/// Gap between a new player and the one who was there first
+pub static JOIN_OFFSET: f32 = 30.0;
+
+/// Colour marking a player's crosshair and HUD panel
+pub fn player_color(number: usize) -> Color {
+    match number {
+        0 => Color::WHITE,
+        1 => Color::rgb(0.4, 0.7, 1.0),
+        2 => Color::rgb(1.0, 0.6, 0.3),
+        _ => Color::rgb(0.6, 1.0, 0.5),
+    }
+}
+
+/// Lowest player number nobody has, so numbers get reused after someone leaves
+pub fn free_player_number(taken: &[usize]) -> usize {
+    (0..).find(|number| !taken.contains(number)).unwrap_or(0)
+}
+
+/// A player out of health, waiting for a teammate to pick them up
+pub struct Downed {
+    revive_seconds: f32,
+}
+
+impl Downed {
+    pub fn revive_progress(&self) -> f32 {
+        self.revive_seconds / REVIVE_SECONDS
+    }
+}
+
+/// Pressing start on a gamepad nobody else is using adds a player for it
+pub fn join_players(
+    mut commands: Commands,
+    sprite_sheets: Res<SpriteSheets>,
+    connected: Res<ConnectedGamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    mut player_query: Query<(&Player, &mut GamepadInput, &Transform)>,
+) {
+    let mut taken: Vec<usize> = player_query.iter_mut().map(|(player, ..)| player.number).collect();
+
+    for gamepad in connected.0.iter() {
+        if taken.len() >= MAX_PLAYERS || !buttons.just_pressed(GamepadButton(*gamepad, GamepadButtonType::Start)) {
+            continue;
+        }
+
+        // The keyboard player may have picked this gamepad up while alone, hand it over
+        let mut in_use = false;
+        let mut first_location = Vec2::ZERO;
+        for (player, mut input, trans) in player_query.iter_mut() {
+            if player.number == 0 {
+                first_location = trans.translation.truncate();
+            }
+            if input.gamepad == Some(*gamepad) {
+                match input.uses_keyboard_mouse() {
+                    true => input.gamepad = None,
+                    false => in_use = true,
+                }
+            }
+        }
+        if in_use {
+            continue;
+        }
+
+        let number = free_player_number(&taken);
+        let location = first_location + Vec2::new(JOIN_OFFSET * number as f32, 0.0);
+        crate::spawn_player(&mut commands, &sprite_sheets, number, GamepadInput::gamepad(*gamepad), location);
+        taken.push(number);
+    }
+}
+
+/// Knocks players whose health ran out to the ground
+pub fn down_players(
+    mut commands: Commands,
+    mut player_query: Query<(&Health, &mut Transform, Entity), (With<Player>, Without<Downed>)>,
+) {
+    for (health, mut trans, entity) in player_query.iter_mut() {
+        if health.is_dead() {
+            trans.rotation = Quat::from_rotation_z(FRAC_PI_2);
+            commands.entity(entity).insert(Downed { revive_seconds: 0.0 });
+        }
+    }
+}
+
+/// Teammates standing over a downed player slowly bring them back up
+pub fn revive_players(
+    mut commands: Commands,
+    time: Res<Time>,
+    helper_query: Query<&Transform, (With<Player>, Without<Downed>)>,
+    mut downed_query: Query<(&mut Downed, &mut Health, &mut Transform, Entity)>,
+) {
+    for (mut downed, mut health, mut trans, entity) in downed_query.iter_mut() {
+        let location = trans.translation.truncate();
+        let helped = helper_query
+            .iter()
+            .any(|helper| (helper.translation.truncate() - location).length() < REVIVE_RADIUS);
+
+        // Progress is lost if the reviver steps away
+        if !helped {
+            downed.revive_seconds = 0.0;
+            continue;
+        }
+
+        downed.revive_seconds += time.delta_seconds();
+        if downed.revive_seconds >= REVIVE_SECONDS {
+            health.current = health.max * REVIVE_HEALTH;
+            trans.rotation = Quat::from_rotation_z(0.0);
+            commands.entity(entity).remove::<Downed>();
+        }
+    }
+}
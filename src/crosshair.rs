@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use cgmath::Rad;
+
+use crate::coop::{self, Downed};
+use crate::gamepad::{self, GamepadInput};
+use crate::settings::{CrosshairStyle, Settings};
+use crate::{GameState, MainCamera, Player};
+
+static CROSSHAIR_Z: f32 = 0.9;
+static SIGHT_SIZE: f32 = 24.0;
+static DOT_SIZE: f32 = 4.0;
+static BAR_LENGTH: f32 = 8.0;
+static BAR_WIDTH: f32 = 2.0;
+/// Gap between the cross bars when the gun is perfectly accurate
+static MIN_GAP: f32 = 4.0;
+
+/// Root of a player's crosshair, following that player's aim
+pub struct Crosshair(Entity);
+
+enum CrosshairPart {
+    Sight,
+    Dot,
+    /// One arm of the cross, pointing away from the centre
+    Bar(Vec2),
+}
+
+/// World location of the cursor, as seen through `camera`
+pub fn cursor_world_location(window: &Window, camera: &Transform) -> Option<Vec2> {
+    window.cursor_position().map(|cursor| {
+        let from_centre = cursor - Vec2::new(window.width(), window.height()) / 2.0;
+        camera.mul_vec3(from_centre.extend(0.0)).truncate()
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_crosshair(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
+    player_query: Query<(&Player, &GamepadInput, &Rad<f32>, &Transform, Option<&Downed>)>,
+    camera_query: Query<&Transform, (With<MainCamera>, Without<Crosshair>, Without<CrosshairPart>)>,
+    mut crosshair_query: Query<(&Crosshair, &mut Transform, &Children, Entity), Without<Player>>,
+    mut part_query: Query<
+        (&CrosshairPart, &mut Sprite, &mut Transform, &mut Visible),
+        (Without<Crosshair>, Without<Player>),
+    >,
+) {
+    let window = windows.get_primary();
+    let camera = match camera_query.single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    for (crosshair, mut crosshair_trans, children, entity) in crosshair_query.iter_mut() {
+        let (player, gamepad, angle, player_trans, downed) = match player_query.get(crosshair.0) {
+            Ok(player) => player,
+            Err(_) => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+        let player_location = player_trans.translation.truncate();
+        let cursor = window.and_then(|window| gamepad::aim_target(gamepad, window, camera, player_location, *angle));
+
+        // Menus use the system cursor instead
+        let style = match (cursor, state.current(), downed) {
+            (Some(_), GameState::Playing, None) => Some(settings.crosshair),
+            _ => None,
+        };
+        let cursor = cursor.unwrap_or(Vec2::ZERO);
+        crosshair_trans.translation = cursor.extend(CROSSHAIR_Z);
+
+        // Widen to cover where shots can land at the cursor's distance
+        let spread = player.gun.as_ref().map(|gun| gun.spread()).unwrap_or(0.0);
+        let gap = ((cursor - player_location).length() * spread.tan()).max(MIN_GAP);
+
+        for child in children.iter() {
+            let (part, mut sprite, mut trans, mut visible) = match part_query.get_mut(*child) {
+                Ok(part) => part,
+                Err(_) => continue,
+            };
+
+            match part {
+                CrosshairPart::Sight => {
+                    visible.is_visible = style == Some(CrosshairStyle::Sight);
+                    sprite.size = Vec2::splat(SIGHT_SIZE + (gap - MIN_GAP) * 2.0);
+                }
+                CrosshairPart::Dot => {
+                    visible.is_visible = style == Some(CrosshairStyle::Dot);
+                }
+                CrosshairPart::Bar(direction) => {
+                    visible.is_visible = style == Some(CrosshairStyle::Cross);
+                    trans.translation = (*direction * (gap + BAR_LENGTH / 2.0)).extend(0.0);
+                }
+            }
+        }
+    }
+}
+
+/// Gives each player who joins a crosshair in their colour
+pub fn add_crosshairs(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    player_query: Query<(&Player, Entity), Added<Player>>,
+) {
+    for (player, entity) in player_query.iter() {
+        let color = coop::player_color(player.number);
+        let sight = materials.add(ColorMaterial::modulated_texture(
+            asset_server.load("images/sights/default.png"),
+            color,
+        ));
+        let solid = materials.add(color.into());
+
+        commands
+            .spawn_bundle((
+                Crosshair(entity),
+                Transform::from_xyz(0.0, 0.0, CROSSHAIR_Z),
+                GlobalTransform::identity(),
+            ))
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite::new(Vec2::splat(SIGHT_SIZE)),
+                        material: sight,
+                        ..Default::default()
+                    })
+                    .insert(CrosshairPart::Sight);
+
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite::new(Vec2::splat(DOT_SIZE)),
+                        material: solid.clone(),
+                        ..Default::default()
+                    })
+                    .insert(CrosshairPart::Dot);
+
+                let directions = [
+                    Vec2::new(1.0, 0.0),
+                    Vec2::new(0.0, 1.0),
+                    Vec2::new(-1.0, 0.0),
+                    Vec2::new(0.0, -1.0),
+                ];
+                for direction in directions.iter() {
+                    let size = match direction.x == 0.0 {
+                        true => Vec2::new(BAR_WIDTH, BAR_LENGTH),
+                        false => Vec2::new(BAR_LENGTH, BAR_WIDTH),
+                    };
+
+                    parent
+                        .spawn_bundle(SpriteBundle {
+                            sprite: Sprite::new(size),
+                            material: solid.clone(),
+                            ..Default::default()
+                        })
+                        .insert(CrosshairPart::Bar(*direction));
+                }
+            });
+    }
+}
@@ -0,0 +1,155 @@
+use bevy::prelude::*;
+use rand::{self, Rng};
+
+use crate::animation::CharacterAnimation;
+use crate::boss::Boss;
+use crate::events::{ZomHit, ZomKilled};
+use crate::particles::{ParticleEmitter, ParticlePreset};
+use crate::perception::Perception;
+use crate::{Materials, Zom};
+
+use std::collections::VecDeque;
+use std::f32::consts::FRAC_PI_2;
+
+static CORPSE_Z: f32 = 0.01;
+static DECAL_Z: f32 = 0.005;
+/// Blood sprays a dying zombie gives off each second
+static DYING_BLEED_RATE: f32 = 6.0;
+
+pub struct DeathConfig {
+    /// How long the dying animation takes before the body becomes a corpse
+    pub dying_seconds: f32,
+    /// How long a corpse stays before it is removed, including the fade
+    pub corpse_seconds: f32,
+    pub corpse_fade_seconds: f32,
+    /// Oldest blood decals are removed once there are more than this many
+    pub max_blood_decals: usize,
+}
+
+impl Default for DeathConfig {
+    fn default() -> Self {
+        DeathConfig {
+            dying_seconds: 0.5,
+            corpse_seconds: 8.0,
+            corpse_fade_seconds: 2.0,
+            max_blood_decals: 150,
+        }
+    }
+}
+
+pub struct Dying(Timer);
+
+pub struct Corpse(Timer);
+
+/// Live blood decals, oldest first
+#[derive(Default)]
+pub struct BloodDecals(VecDeque<Entity>);
+
+/// Stops a zombie from acting and starts its death sequence
+fn kill_zom(commands: &mut Commands, config: &DeathConfig, entity: Entity) {
+    commands
+        .entity(entity)
+        .remove::<Zom>()
+        .remove::<Boss>()
+        .remove::<Perception>()
+        .insert(ParticleEmitter::new(ParticlePreset::BloodSpray, DYING_BLEED_RATE))
+        .insert(Dying(Timer::from_seconds(config.dying_seconds, false)));
+}
+
+fn spawn_blood(
+    commands: &mut Commands,
+    materials: &Materials,
+    config: &DeathConfig,
+    decals: &mut BloodDecals,
+    location: Vec2,
+) {
+    let mut random = rand::thread_rng();
+    let size = random.gen_range(6.0..14.0);
+    let offset = Vec2::new(random.gen_range(-4.0..4.0), random.gen_range(-4.0..4.0));
+
+    let mut transform = Transform::from_xyz(location.x + offset.x, location.y + offset.y, DECAL_Z);
+    transform.rotate(Quat::from_rotation_z(random.gen_range(0.0..FRAC_PI_2)));
+
+    let decal = commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite::new(Vec2::new(size, size * random.gen_range(0.5..1.0))),
+            material: materials.blood.clone(),
+            transform,
+            ..Default::default()
+        })
+        .id();
+    decals.0.push_back(decal);
+
+    while decals.0.len() > config.max_blood_decals {
+        if let Some(oldest) = decals.0.pop_front() {
+            commands.entity(oldest).despawn();
+        }
+    }
+}
+
+pub fn bleed(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    config: Res<DeathConfig>,
+    mut decals: ResMut<BloodDecals>,
+    mut hit_reader: EventReader<ZomHit>,
+) {
+    for hit in hit_reader.iter() {
+        spawn_blood(&mut commands, &materials, &config, &mut decals, hit.location);
+    }
+}
+
+pub fn kill_zoms(mut commands: Commands, config: Res<DeathConfig>, mut killed_reader: EventReader<ZomKilled>) {
+    for killed in killed_reader.iter() {
+        kill_zom(&mut commands, &config, killed.zom);
+    }
+}
+
+pub fn start_death_animation(mut query: Query<&mut CharacterAnimation, Added<Dying>>) {
+    for mut animation in query.iter_mut() {
+        animation.play_once("death");
+    }
+}
+
+pub fn update_dying(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<DeathConfig>,
+    mut query: Query<(&mut Dying, &mut Transform, Entity)>,
+) {
+    for (mut dying, mut trans, entity) in query.iter_mut() {
+        dying.0.tick(time.delta());
+
+        // Topple over as the death frames play
+        trans.rotation = Quat::from_rotation_z(FRAC_PI_2 * dying.0.percent());
+
+        if dying.0.finished() {
+            trans.translation.z = CORPSE_Z;
+            commands
+                .entity(entity)
+                .remove::<Dying>()
+                .remove::<ParticleEmitter>()
+                .insert(Corpse(Timer::from_seconds(config.corpse_seconds, false)));
+        }
+    }
+}
+
+pub fn fade_corpses(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<DeathConfig>,
+    mut query: Query<(&mut Corpse, &mut TextureAtlasSprite, Entity)>,
+) {
+    for (mut corpse, mut sprite, entity) in query.iter_mut() {
+        corpse.0.tick(time.delta());
+
+        let remaining = corpse.0.duration().as_secs_f32() - corpse.0.elapsed_secs();
+        if remaining < config.corpse_fade_seconds {
+            sprite.color.set_a((remaining / config.corpse_fade_seconds).max(0.0));
+        }
+
+        if corpse.0.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
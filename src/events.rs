@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use cgmath::Rad;
+
+use crate::ZomType;
+
+/// A gun went off
+pub struct ShotFired {
+    pub origin: Vec2,
+    pub angle: Rad<f32>,
+    /// How far away zombies can hear the shot, in pixels
+    pub loudness: f32,
+    /// How far the shot pushes the camera back, in pixels
+    pub camera_kick: f32,
+}
+
+/// A bullet hit a zombie that was still alive
+pub struct ZomHit {
+    pub zom_type: ZomType,
+    pub location: Vec2,
+    /// Unit vector of the bullet's travel
+    pub direction: Vec2,
+    pub damage: f32,
+}
+
+/// A zombie's health ran out
+pub struct ZomKilled {
+    pub zom: Entity,
+    pub zom_type: ZomType,
+    pub location: Vec2,
+}
+
+/// Something damaged a player, applied by `hurt_player`
+pub struct PlayerHurt {
+    pub player: Entity,
+    pub amount: f32,
+    /// Where the damage came from
+    pub source: Vec2,
+}
@@ -0,0 +1,203 @@
+use bevy::input::gamepad::{Gamepad, GamepadAxis, GamepadAxisType, GamepadButton, GamepadButtonType, GamepadEvent, GamepadEventType};
+use bevy::input::mouse::MouseMotion;
+use bevy::prelude::*;
+use cgmath::{Angle, Rad};
+
+use crate::bindings::Action;
+use crate::net::RemoteInput;
+
+/// Stick deflection ignored as drift
+static STICK_DEAD_ZONE: f32 = 0.2;
+/// How far in front of the player the crosshair sits when aiming with a stick
+static GAMEPAD_AIM_DISTANCE: f32 = 150.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum InputDevice {
+    Mouse,
+    Gamepad,
+}
+
+/// Gamepads currently plugged in, in the order they connected
+#[derive(Default)]
+pub struct ConnectedGamepads(pub Vec<Gamepad>);
+
+/// How one player is controlled, and whether their gamepad or the mouse was touched last
+pub struct GamepadInput {
+    pub gamepad: Option<Gamepad>,
+    /// Only the first player reads the keyboard and mouse
+    keyboard_mouse: bool,
+    /// Left stick, with the dead zone taken out
+    pub movement: Vec2,
+    /// Right stick direction, if it's pushed past the dead zone
+    pub aim: Option<Vec2>,
+    pub device: InputDevice,
+}
+
+impl GamepadInput {
+    pub fn keyboard_mouse() -> Self {
+        GamepadInput {
+            gamepad: None,
+            keyboard_mouse: true,
+            movement: Vec2::ZERO,
+            aim: None,
+            device: InputDevice::Mouse,
+        }
+    }
+
+    pub fn gamepad(gamepad: Gamepad) -> Self {
+        GamepadInput {
+            gamepad: Some(gamepad),
+            keyboard_mouse: false,
+            movement: Vec2::ZERO,
+            aim: None,
+            device: InputDevice::Gamepad,
+        }
+    }
+
+    /// A player on another machine, whose sticks arrive over the network
+    pub fn remote() -> Self {
+        GamepadInput {
+            gamepad: None,
+            keyboard_mouse: false,
+            movement: Vec2::ZERO,
+            aim: None,
+            device: InputDevice::Gamepad,
+        }
+    }
+
+    pub fn uses_keyboard_mouse(&self) -> bool {
+        self.keyboard_mouse
+    }
+
+    /// Reads the gamepad's sticks into `movement` and `aim`
+    fn read_sticks(&mut self, axes: &Axis<GamepadAxis>) {
+        let gamepad = match self.gamepad {
+            Some(gamepad) => gamepad,
+            None => {
+                self.movement = Vec2::ZERO;
+                self.aim = None;
+                return;
+            }
+        };
+
+        let stick = |x, y| {
+            let axis = |axis_type| axes.get(GamepadAxis(gamepad, axis_type)).unwrap_or(0.0);
+            apply_dead_zone(Vec2::new(axis(x), axis(y)))
+        };
+        self.movement = stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+        let aim = stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+        self.aim = match aim == Vec2::ZERO {
+            true => None,
+            false => Some(aim),
+        };
+    }
+
+    pub fn action_pressed(&self, action: Action, buttons: &Input<GamepadButton>) -> bool {
+        let gamepad = match self.gamepad {
+            Some(gamepad) => gamepad,
+            None => return false,
+        };
+
+        let button = match action {
+            Action::Fire => GamepadButtonType::RightTrigger2,
+            Action::Reload => GamepadButtonType::LeftTrigger2,
+            Action::Dash => GamepadButtonType::South,
+            Action::Sprint => GamepadButtonType::LeftThumb,
+            Action::SwitchWeapon => GamepadButtonType::North,
+            Action::ThrowGrenade => GamepadButtonType::RightTrigger,
+            Action::Melee => GamepadButtonType::LeftTrigger,
+            // Movement comes from the left stick
+            Action::MoveUp | Action::MoveDown | Action::MoveLeft | Action::MoveRight => return false,
+        };
+        buttons.pressed(GamepadButton(gamepad, button))
+    }
+}
+
+/// Where the player is aiming, the cursor or a point along the right stick
+pub fn aim_target(
+    gamepad: &GamepadInput,
+    window: &Window,
+    camera: &Transform,
+    player_location: Vec2,
+    angle: Rad<f32>,
+) -> Option<Vec2> {
+    match gamepad.device {
+        InputDevice::Mouse => crate::crosshair::cursor_world_location(window, camera),
+        InputDevice::Gamepad => Some(player_location + Vec2::new(angle.cos(), angle.sin()) * GAMEPAD_AIM_DISTANCE),
+    }
+}
+
+/// Scales a stick so the dead zone reads as zero and full tilt as one
+fn apply_dead_zone(stick: Vec2) -> Vec2 {
+    let length = stick.length();
+    if length < STICK_DEAD_ZONE {
+        return Vec2::ZERO;
+    }
+
+    stick / length * ((length - STICK_DEAD_ZONE) / (1.0 - STICK_DEAD_ZONE)).min(1.0)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_gamepads(
+    mut gamepad_events: EventReader<GamepadEvent>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    axes: Res<Axis<GamepadAxis>>,
+    buttons: Res<Input<GamepadButton>>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut connected: ResMut<ConnectedGamepads>,
+    mut input_query: Query<&mut GamepadInput, Without<RemoteInput>>,
+) {
+    for GamepadEvent(gamepad, event) in gamepad_events.iter() {
+        match event {
+            GamepadEventType::Connected => connected.0.push(*gamepad),
+            GamepadEventType::Disconnected => {
+                connected.0.retain(|connected| connected != gamepad);
+                for mut input in input_query.iter_mut() {
+                    if input.gamepad == Some(*gamepad) {
+                        input.gamepad = None;
+                        if input.keyboard_mouse {
+                            input.device = InputDevice::Mouse;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Playing alone, the keyboard player can pick up any free gamepad
+    let players = input_query.iter_mut().count();
+    let claimed: Vec<Gamepad> = input_query.iter_mut().filter_map(|input| input.gamepad).collect();
+    let free = connected.0.iter().find(|gamepad| !claimed.contains(gamepad)).copied();
+
+    let mouse_used = keys.get_just_pressed().next().is_some()
+        || mouse.get_just_pressed().next().is_some()
+        || mouse_motion.iter().next().is_some();
+
+    for mut input in input_query.iter_mut() {
+        if !input.keyboard_mouse {
+            input.read_sticks(&axes);
+            continue;
+        }
+
+        if input.gamepad.is_none() && players == 1 {
+            input.gamepad = free;
+        }
+        input.read_sticks(&axes);
+
+        // Whichever device was used last drives aiming
+        let gamepad_used = input
+            .gamepad
+            .map(|gamepad| buttons.get_just_pressed().any(|button| button.0 == gamepad))
+            .unwrap_or(false)
+            || input.movement != Vec2::ZERO
+            || input.aim.is_some();
+
+        if gamepad_used {
+            input.device = InputDevice::Gamepad;
+        } else if mouse_used {
+            input.device = InputDevice::Mouse;
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use cgmath::Rad;
+use std::f32::consts::PI;
+
+use crate::bindings::{Action, PlayerActions};
+use crate::coop::Downed;
+use crate::events::{ZomHit, ZomKilled};
+use crate::gamepad::{self, GamepadInput};
+use crate::particles::{EmitParticles, ParticlePreset};
+use crate::perception::Noise;
+use crate::wall::{self, Wall};
+use crate::{Health, HitReaction, MainCamera, Materials, Player, Zom};
+
+pub static START_GRENADES: u32 = 3;
+static GRENADE_SIZE: f32 = 8.0;
+static GRENADE_Z: f32 = 0.15;
+static MAX_THROW: f32 = 350.0;
+/// Pixels per second along the ground
+static THROW_SPEED: f32 = 400.0;
+/// Even a grenade dropped at the thrower's feet spends this long in the air
+static MIN_FLIGHT_SECONDS: f32 = 0.1;
+/// Peak height of the arc at full range, in pixels
+static ARC_HEIGHT: f32 = 60.0;
+static FUSE_SECONDS: f32 = 1.5;
+static BLAST_RADIUS: f32 = 120.0;
+static BLAST_DAMAGE: f32 = 60.0;
+static BLAST_KNOCKBACK: f32 = 12.0;
+/// How far away zombies hear the explosion, in pixels
+static BLAST_LOUDNESS: f32 = 1000.0;
+
+pub struct Grenade {
+    from: Vec2,
+    to: Vec2,
+    flight: Timer,
+    fuse: Timer,
+}
+
+impl Grenade {
+    /// Where the grenade is over the ground and how high it is
+    fn position(&self) -> (Vec2, f32) {
+        let progress = self.flight.percent();
+        let ground = self.from.lerp(self.to, progress);
+        let peak = ARC_HEIGHT * (self.to - self.from).length() / MAX_THROW;
+
+        (ground, peak * (progress * PI).sin())
+    }
+}
+
+pub fn throw_grenade(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    materials: Res<Materials>,
+    wall_query: Query<(&Wall, &Transform)>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut player_query: Query<(&mut Player, &PlayerActions, &GamepadInput, &Rad<f32>, &Transform), Without<Downed>>,
+) {
+    let (window, camera) = match (windows.get_primary(), camera_query.single()) {
+        (Some(window), Ok(camera)) => (window, camera),
+        _ => return,
+    };
+
+    for (mut player, actions, gamepad, angle, player_trans) in player_query.iter_mut() {
+        if !actions.just_pressed(Action::ThrowGrenade) || player.grenades == 0 {
+            continue;
+        }
+        let from = player_trans.translation.truncate();
+        let target = match gamepad::aim_target(gamepad, window, camera, from, *angle) {
+            Some(target) => target,
+            None => continue,
+        };
+        if !target.x.is_finite() || !target.y.is_finite() {
+            continue;
+        }
+        player.grenades -= 1;
+
+        let mut to = target;
+        let throw = to - from;
+        if throw.length() > MAX_THROW {
+            to = from + throw.normalize() * MAX_THROW;
+        }
+        // Bounce off short of any wall in the way
+        if let Some((fraction, normal)) = wall::first_hit(&wall_query, from, to) {
+            to = from + (to - from) * fraction + normal * GRENADE_SIZE;
+        }
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite::new(Vec2::splat(GRENADE_SIZE)),
+                material: materials.grenade.clone(),
+                transform: Transform::from_translation(from.extend(GRENADE_Z)),
+                ..Default::default()
+            })
+            .insert(Grenade {
+                from,
+                to,
+                flight: Timer::from_seconds(((to - from).length() / THROW_SPEED).max(MIN_FLIGHT_SECONDS), false),
+                fuse: Timer::from_seconds(FUSE_SECONDS, false),
+            });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_grenades(
+    mut commands: Commands,
+    time: Res<Time>,
+    wall_query: Query<(&Wall, &Transform)>,
+    mut grenade_query: Query<(&mut Grenade, &mut Transform, Entity), Without<Wall>>,
+    mut zom_query: Query<(&Zom, &Transform, &mut Health, &mut HitReaction, Entity), Without<Grenade>>,
+    mut hit_writer: EventWriter<ZomHit>,
+    mut killed_writer: EventWriter<ZomKilled>,
+    mut particle_writer: EventWriter<EmitParticles>,
+    mut noise_writer: EventWriter<Noise>,
+) {
+    for (mut grenade, mut trans, entity) in grenade_query.iter_mut() {
+        grenade.flight.tick(time.delta());
+        grenade.fuse.tick(time.delta());
+
+        // Draw it higher up the screen and bigger while in the air
+        let (ground, height) = grenade.position();
+        trans.translation = (ground + Vec2::new(0.0, height)).extend(GRENADE_Z);
+        trans.scale = Vec3::splat(1.0 + height / ARC_HEIGHT);
+
+        if !grenade.fuse.finished() {
+            continue;
+        }
+        commands.entity(entity).despawn();
+
+        for (zom, zom_trans, mut health, mut reaction, zom_entity) in zom_query.iter_mut() {
+            let location = zom_trans.translation.truncate();
+            let offset = location - ground;
+            let dist = offset.length();
+            if health.is_dead() || dist > BLAST_RADIUS || !wall::line_of_sight(&wall_query, ground, location) {
+                continue;
+            }
+
+            // Never let a bad position turn into NaN damage
+            let falloff = 1.0 - dist / BLAST_RADIUS;
+            let damage = BLAST_DAMAGE * falloff;
+            if !damage.is_finite() {
+                continue;
+            }
+
+            let direction = match dist > f32::EPSILON {
+                true => offset / dist,
+                false => Vec2::new(0.0, 1.0),
+            };
+            let (_size, mass) = zom.zom_type.size_and_mass();
+
+            health.damage(damage);
+            reaction.hit(direction * BLAST_KNOCKBACK * falloff / mass);
+
+            hit_writer.send(ZomHit {
+                zom_type: zom.zom_type,
+                location,
+                direction,
+                damage,
+            });
+
+            if health.is_dead() {
+                killed_writer.send(ZomKilled {
+                    zom: zom_entity,
+                    zom_type: zom.zom_type,
+                    location,
+                });
+            }
+        }
+
+        particle_writer.send(EmitParticles {
+            preset: ParticlePreset::Explosion,
+            location: ground,
+            direction: 0.0,
+        });
+        noise_writer.send(Noise {
+            origin: ground,
+            radius: BLAST_LOUDNESS,
+        });
+    }
+}
@@ -1,192 +1,636 @@
 use bevy::prelude::*;
-use cgmath::{self, Angle, Rad};
+use cgmath::{self, Rad};
 use rand::{self, Rng};
 
-pub trait Gun: Send + Sync {
-    fn new() -> Box<Self>
-    where
-        Self: Sized;
+use std::f32::consts::{FRAC_PI_4, PI};
 
-    fn shoot(
-        &mut self,
-        time: Res<Time>,
-        mouse: Res<Input<MouseButton>>,
-        player_transform: &Transform,
-        angle: Rad<f32>,
-        materials: Res<crate::Materials>,
-        commands: Commands,
-    );
+static BULLET_SPEED: f32 = 6.0;
 
-    fn left_in_mag(&self) -> u16;
+/// How a gun's accuracy reacts to sustained fire
+#[derive(Clone)]
+pub struct Recoil {
+    /// Spread of a fresh, steady gun in radians
+    base_spread: f32,
+    bloom_per_shot: f32,
+    max_bloom: f32,
+    /// Radians of bloom lost each second
+    recovery: f32,
+    /// Extra spread while the player is moving
+    moving_spread: f32,
+    /// How far each shot pushes the camera, in pixels
+    camera_kick: f32,
+    bloom: f32,
+    moving: bool,
+}
 
-    fn reload(&mut self);
+impl Recoil {
+    fn spread(&self) -> f32 {
+        let moving = if self.moving { self.moving_spread } else { 0.0 };
+        self.base_spread + self.bloom + moving
+    }
 
-    fn name(&self) -> &str;
+    /// Random angle off the aim for one projectile
+    fn deviation(&self, random: &mut impl Rng) -> f32 {
+        let spread = self.spread();
+        random.gen_range(-spread..=spread)
+    }
 
-    fn reloading(&self) -> bool;
+    fn fire(&mut self) {
+        self.bloom = (self.bloom + self.bloom_per_shot).min(self.max_bloom);
+    }
+
+    /// Recovers accuracy over `delta` seconds
+    pub fn settle(&mut self, delta: f32, moving: bool) {
+        self.bloom = (self.bloom - self.recovery * delta).max(0.0);
+        self.moving = moving;
+    }
+
+    pub fn camera_kick(&self) -> f32 {
+        self.camera_kick
+    }
 }
 
-pub struct Shotgun {
-    time_left: f32,
-    mag_size: u16,
-    reloading: bool,
+#[derive(Clone, Copy)]
+pub enum ReloadStyle {
+    /// The whole magazine is swapped after a single wait
+    Magazine { seconds: f32 },
+    /// Rounds go in one at a time, firing stops the reload
+    PerRound { seconds_per_round: f32 },
 }
 
-pub struct Pistol {
-    time_left: f32,
-    mag_size: u16,
-    reloading: bool,
+pub struct Magazine {
+    capacity: u16,
+    loaded: u16,
+    /// Rounds carried outside the magazine
+    reserve: u16,
+    style: ReloadStyle,
+    /// Seconds into the current reload step, `None` when not reloading
+    reload_elapsed: Option<f32>,
 }
 
-impl Gun for Shotgun {
-    fn new() -> Box<Self>
-    where
-        Self: Sized,
-    {
-        Box::new(Shotgun {
-            time_left: 0.2,
-            mag_size: 2,
-            reloading: false,
+impl Magazine {
+    fn new(capacity: u16, reserve: u16, style: ReloadStyle) -> Self {
+        Magazine {
+            capacity,
+            loaded: capacity,
+            reserve,
+            style,
+            reload_elapsed: None,
+        }
+    }
+
+    fn step_seconds(&self) -> f32 {
+        match self.style {
+            ReloadStyle::Magazine { seconds } => seconds,
+            ReloadStyle::PerRound { seconds_per_round } => seconds_per_round,
+        }
+    }
+
+    /// Starts a reload unless one is running or there is nothing to load
+    fn start_reload(&mut self) {
+        if self.reload_elapsed.is_none() && self.loaded < self.capacity && self.reserve > 0 {
+            self.reload_elapsed = Some(0.0);
+        }
+    }
+
+    fn tick(&mut self, delta: f32) {
+        let step = self.step_seconds();
+        let elapsed = match self.reload_elapsed {
+            Some(elapsed) => elapsed + delta,
+            None => return,
+        };
+        if elapsed < step {
+            self.reload_elapsed = Some(elapsed);
+            return;
+        }
+
+        let rounds = match self.style {
+            ReloadStyle::Magazine { .. } => self.capacity - self.loaded,
+            ReloadStyle::PerRound { .. } => 1,
+        }
+        .min(self.reserve);
+        self.loaded += rounds;
+        self.reserve -= rounds;
+
+        self.reload_elapsed = match self.loaded < self.capacity && self.reserve > 0 {
+            true => Some(elapsed - step),
+            false => None,
+        };
+    }
+
+    /// Takes a round to fire, stopping a reload that can be interrupted
+    fn take_round(&mut self) -> bool {
+        if self.reload_elapsed.is_some() {
+            match self.style {
+                ReloadStyle::PerRound { .. } if self.loaded > 0 => self.reload_elapsed = None,
+                _ => return false,
+            }
+        }
+        if self.loaded == 0 {
+            return false;
+        }
+
+        self.loaded -= 1;
+        true
+    }
+
+    /// Adds `magazines` full magazines' worth of rounds to the reserve
+    fn add_reserve(&mut self, magazines: u16) {
+        self.reserve = self.reserve.saturating_add(self.capacity * magazines);
+    }
+
+    /// Rounds loaded, rounds in reserve and seconds into the current reload step
+    pub fn state(&self) -> (u16, u16, Option<f32>) {
+        (self.loaded, self.reserve, self.reload_elapsed)
+    }
+
+    /// Overwrites the rounds and reload, for clients showing the server's copy of the gun
+    pub fn set_state(&mut self, loaded: u16, reserve: u16, reload_elapsed: Option<f32>) {
+        self.loaded = loaded.min(self.capacity);
+        self.reserve = reserve;
+        self.reload_elapsed = reload_elapsed;
+    }
+
+    fn progress(&self) -> Option<f32> {
+        let step = (self.reload_elapsed? / self.step_seconds()).min(1.0);
+
+        Some(match self.style {
+            ReloadStyle::Magazine { .. } => step,
+            ReloadStyle::PerRound { .. } => (self.loaded as f32 + step) / self.capacity as f32,
         })
     }
+}
 
-    fn name(&self) -> &str {
-        "Shotgun"
+/// How a gun's bullets deal damage as they travel
+#[derive(Clone, Copy)]
+pub struct Ballistics {
+    pub damage: f32,
+    pub knockback: f32,
+    /// Zombies a bullet can pass through after the first
+    pub penetration: u32,
+    /// Fraction of damage kept after passing through a zombie
+    pub penetration_damage: f32,
+    /// Distance in pixels bullets keep full damage for
+    pub falloff_start: f32,
+    /// Distance in pixels at which damage stops dropping
+    pub falloff_end: f32,
+    /// Fraction of damage left past `falloff_end`
+    pub min_damage: f32,
+    /// Times a bullet bounces off walls before stopping
+    pub ricochets: u32,
+}
+
+impl Ballistics {
+    /// Damage of a bullet that has flown `travelled` pixels
+    pub fn damage_at(&self, travelled: f32) -> f32 {
+        let range = self.falloff_end - self.falloff_start;
+        let falloff = ((travelled - self.falloff_start) / range).clamp(0.0, 1.0);
+        self.damage * (1.0 - falloff * (1.0 - self.min_damage))
     }
 
+    /// The same ballistics with damage scaled by `factor`
+    fn boosted(&self, factor: f32) -> Ballistics {
+        Ballistics {
+            damage: self.damage * factor,
+            ..*self
+        }
+    }
+}
+
+/// Where a shot leaves from and how hard it hits, decided by whoever holds the gun
+#[derive(Clone, Copy)]
+pub struct Aim {
+    pub muzzle: Vec2,
+    pub angle: Rad<f32>,
+    /// Damage multiplier from the holder's buffs
+    pub damage_factor: f32,
+}
+
+/// Sends a bullet out of the muzzle along `angle`, in radians
+fn spawn_bullet(commands: &mut Commands, materials: &crate::Materials, muzzle: Vec2, angle: f32, ballistics: Ballistics) {
+    let mut transform = Transform::from_translation(muzzle.extend(0.0));
+    transform.rotate(Quat::from_rotation_z(angle));
+
+    commands
+        .spawn()
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite::new(Vec2::new(10.0, 4.0)),
+            material: materials.bullet.clone(),
+            transform,
+            ..Default::default()
+        })
+        .insert(crate::Bullet::new(ballistics))
+        .insert(crate::Vel(Vec2::new(angle.cos(), angle.sin()) * BULLET_SPEED));
+}
+
+pub trait Gun: Send + Sync {
+    /// Fires if `trigger` is pulled and the gun is ready, returning whether it did
     fn shoot(
         &mut self,
-        time: Res<Time>,
-        mouse: Res<Input<MouseButton>>,
-        player_transform: &Transform,
-        angle: Rad<f32>,
-        materials: Res<crate::Materials>,
-        mut commands: Commands,
-    ) {
-        self.time_left -= time.delta_seconds();
-        if self.time_left <= 0.0 {
-            self.reloading = false;
-            if mouse.just_pressed(MouseButton::Left) {
-                let mut random = rand::thread_rng();
-                for _index in 0..5 {
-                    // #[allow(clippy::clone_on_copy)]
-                    let mut transform = *player_transform;
-                    transform.translation.z = 0.0;
-                    transform.rotate(Quat::from_rotation_z(angle.0));
+        time: &Time,
+        trigger: bool,
+        aim: Aim,
+        materials: &crate::Materials,
+        commands: &mut Commands,
+    ) -> bool;
 
-                    commands
-                        .spawn()
-                        .insert_bundle(SpriteBundle {
-                            sprite: Sprite::new(Vec2::new(10.0, 4.0)),
-                            material: materials.bullet.clone(),
-                            transform,
-                            ..Default::default()
-                        })
-                        .insert(crate::Bullet {})
-                        .insert(crate::Vel(Vec2::new(
-                            (angle.cos() + random.gen_range(-0.1..=0.1)) * 6.0,
-                            (angle.sin() + random.gen_range(-0.1..=0.1)) * 6.0,
-                        )));
-                }
+    fn magazine(&self) -> &Magazine;
 
-                self.mag_size -= 1;
+    fn magazine_mut(&mut self) -> &mut Magazine;
 
-                // Set time before next possible shot
-                if self.mag_size == 0 {
-                    self.reload();
-                } else {
-                    self.time_left = 0.5;
-                }
-            }
-        }
+    fn left_in_mag(&self) -> u16 {
+        self.magazine().loaded
     }
 
+    /// Rounds carried outside the magazine
+    fn reserve(&self) -> u16 {
+        self.magazine().reserve
+    }
+
+    /// How far away zombies can hear a shot, in pixels
+    fn loudness(&self) -> f32;
+
+    fn recoil(&self) -> &Recoil;
+
+    fn recoil_mut(&mut self) -> &mut Recoil;
+
+    /// Half angle of the cone shots currently land in, in radians
+    fn spread(&self) -> f32 {
+        self.recoil().spread()
+    }
+
+    /// Starts reloading, does nothing if the magazine is full
     fn reload(&mut self) {
-        self.time_left = 1.0;
-        self.mag_size = 2;
-        self.reloading = true;
+        self.magazine_mut().start_reload();
     }
 
+    /// Keeps firing while the trigger is held instead of once per pull
+    fn automatic(&self) -> bool {
+        false
+    }
+
+    /// Tops up the reserve by `magazines` magazines
+    fn add_ammo(&mut self, magazines: u16) {
+        self.magazine_mut().add_reserve(magazines);
+    }
+
+    fn name(&self) -> &str;
+
     fn reloading(&self) -> bool {
-        self.reloading
+        self.magazine().reload_elapsed.is_some()
     }
 
-    fn left_in_mag(&self) -> u16 {
-        self.mag_size
+    /// How far through the current reload the gun is, from 0 to 1
+    fn reload_progress(&self) -> Option<f32> {
+        self.magazine().progress()
     }
+
+    /// Image shown for the gun, relative to the assets folder
+    fn icon(&self) -> &str;
+
+    /// Direction the barrel points in the gun's image, in radians
+    fn icon_angle(&self) -> f32;
+
+    /// Size the gun is drawn at in the player's hands
+    fn held_size(&self) -> Vec2;
+}
+
+/// What a gun sends out each time it fires
+#[derive(Clone, Copy)]
+pub enum Projectile {
+    /// Bullets that travel, `pellets` of them spread across the recoil cone
+    Bullets { pellets: u32 },
+    /// A shot that lands the moment it's fired
+    Hitscan,
+}
+
+/// What sets one gun apart from another
+pub struct GunStats {
+    pub name: &'static str,
+    /// Image shown for the gun, relative to the assets folder
+    icon: &'static str,
+    /// Direction the barrel points in the image, in radians
+    icon_angle: f32,
+    /// Width and height the gun is drawn at in the player's hands
+    held_size: f32,
+    /// Keeps firing while the trigger is held instead of once per pull
+    automatic: bool,
+    /// Seconds between shots
+    fire_interval: f32,
+    loudness: f32,
+    magazine: u16,
+    reserve: u16,
+    reload: ReloadStyle,
+    projectile: Projectile,
+    recoil: Recoil,
+    ballistics: Ballistics,
+}
+
+/// A spread of pellets that hits hard up close
+pub static SHOTGUN: GunStats = GunStats {
+    name: "Shotgun",
+    icon: "images/guns/shotgun.png",
+    icon_angle: FRAC_PI_4,
+    held_size: 36.0,
+    automatic: false,
+    fire_interval: 0.5,
+    loudness: 600.0,
+    magazine: 2,
+    reserve: 24,
+    reload: ReloadStyle::PerRound { seconds_per_round: 0.5 },
+    projectile: Projectile::Bullets { pellets: 5 },
+    recoil: Recoil {
+        base_spread: 0.1,
+        bloom_per_shot: 0.05,
+        max_bloom: 0.2,
+        recovery: 0.3,
+        moving_spread: 0.08,
+        camera_kick: 6.0,
+        bloom: 0.0,
+        moving: false,
+    },
+    ballistics: Ballistics {
+        damage: 10.0,
+        knockback: 3.0,
+        penetration: 0,
+        penetration_damage: 0.0,
+        falloff_start: 150.0,
+        falloff_end: 400.0,
+        min_damage: 0.3,
+        ricochets: 0,
+    },
+};
+
+/// Fires as fast as the trigger is pulled, accuracy blooms quickly
+pub static PISTOL: GunStats = GunStats {
+    name: "Pistol",
+    icon: "images/guns/pistol.png",
+    icon_angle: PI,
+    held_size: 18.0,
+    automatic: false,
+    fire_interval: 0.01,
+    loudness: 350.0,
+    magazine: 7,
+    reserve: 42,
+    reload: ReloadStyle::Magazine { seconds: 0.8 },
+    projectile: Projectile::Bullets { pellets: 1 },
+    recoil: Recoil {
+        base_spread: 0.0,
+        bloom_per_shot: 0.06,
+        max_bloom: 0.35,
+        recovery: 0.5,
+        moving_spread: 0.05,
+        camera_kick: 2.0,
+        bloom: 0.0,
+        moving: false,
+    },
+    ballistics: Ballistics {
+        damage: 10.0,
+        knockback: 1.0,
+        penetration: 1,
+        penetration_damage: 0.6,
+        falloff_start: 300.0,
+        falloff_end: 700.0,
+        min_damage: 0.5,
+        ricochets: 1,
+    },
+};
+
+/// Slow, loud and hitscan, punches through a line of zombies
+pub static RIFLE: GunStats = GunStats {
+    name: "Rifle",
+    icon: "images/guns/rifle.png",
+    icon_angle: FRAC_PI_4,
+    held_size: 40.0,
+    automatic: false,
+    fire_interval: 0.8,
+    loudness: 800.0,
+    magazine: 5,
+    reserve: 30,
+    reload: ReloadStyle::Magazine { seconds: 1.5 },
+    projectile: Projectile::Hitscan,
+    recoil: Recoil {
+        base_spread: 0.0,
+        bloom_per_shot: 0.08,
+        max_bloom: 0.3,
+        recovery: 0.4,
+        moving_spread: 0.12,
+        camera_kick: 8.0,
+        bloom: 0.0,
+        moving: false,
+    },
+    ballistics: Ballistics {
+        damage: 40.0,
+        knockback: 4.0,
+        penetration: 3,
+        penetration_damage: 0.75,
+        falloff_start: 1000.0,
+        falloff_end: 2000.0,
+        min_damage: 0.8,
+        ricochets: 0,
+    },
+};
+
+/// Light and fast firing, weak beyond close range
+pub static SMG: GunStats = GunStats {
+    name: "SMG",
+    icon: "images/guns/submachine_gun.png",
+    icon_angle: PI,
+    held_size: 26.0,
+    automatic: true,
+    fire_interval: 0.07,
+    loudness: 300.0,
+    magazine: 30,
+    reserve: 120,
+    reload: ReloadStyle::Magazine { seconds: 1.2 },
+    projectile: Projectile::Bullets { pellets: 1 },
+    recoil: Recoil {
+        base_spread: 0.05,
+        bloom_per_shot: 0.02,
+        max_bloom: 0.25,
+        recovery: 0.6,
+        moving_spread: 0.04,
+        camera_kick: 1.0,
+        bloom: 0.0,
+        moving: false,
+    },
+    ballistics: Ballistics {
+        damage: 6.0,
+        knockback: 0.5,
+        penetration: 0,
+        penetration_damage: 0.0,
+        falloff_start: 150.0,
+        falloff_end: 400.0,
+        min_damage: 0.4,
+        ricochets: 0,
+    },
+};
+
+/// Steady automatic fire that holds its accuracy
+pub static ASSAULT_RIFLE: GunStats = GunStats {
+    name: "Assault rifle",
+    icon: "images/guns/assault_rifle.png",
+    icon_angle: FRAC_PI_4,
+    held_size: 34.0,
+    automatic: true,
+    fire_interval: 0.12,
+    loudness: 500.0,
+    magazine: 30,
+    reserve: 90,
+    reload: ReloadStyle::Magazine { seconds: 1.6 },
+    projectile: Projectile::Bullets { pellets: 1 },
+    recoil: Recoil {
+        base_spread: 0.02,
+        bloom_per_shot: 0.03,
+        max_bloom: 0.2,
+        recovery: 0.5,
+        moving_spread: 0.08,
+        camera_kick: 2.0,
+        bloom: 0.0,
+        moving: false,
+    },
+    ballistics: Ballistics {
+        damage: 12.0,
+        knockback: 1.5,
+        penetration: 1,
+        penetration_damage: 0.6,
+        falloff_start: 300.0,
+        falloff_end: 700.0,
+        min_damage: 0.5,
+        ricochets: 0,
+    },
+};
+
+/// Hard hitting automatic that kicks more than the assault rifle
+pub static AK_47: GunStats = GunStats {
+    name: "AK-47",
+    icon: "images/guns/ak_47.png",
+    icon_angle: FRAC_PI_4,
+    held_size: 36.0,
+    automatic: true,
+    fire_interval: 0.15,
+    loudness: 650.0,
+    magazine: 30,
+    reserve: 90,
+    reload: ReloadStyle::Magazine { seconds: 2.0 },
+    projectile: Projectile::Bullets { pellets: 1 },
+    recoil: Recoil {
+        base_spread: 0.04,
+        bloom_per_shot: 0.05,
+        max_bloom: 0.3,
+        recovery: 0.4,
+        moving_spread: 0.1,
+        camera_kick: 3.5,
+        bloom: 0.0,
+        moving: false,
+    },
+    ballistics: Ballistics {
+        damage: 18.0,
+        knockback: 2.5,
+        penetration: 1,
+        penetration_damage: 0.7,
+        falloff_start: 250.0,
+        falloff_end: 600.0,
+        min_damage: 0.5,
+        ricochets: 0,
+    },
+};
+
+/// A gun built from one of the stats above
+pub struct Firearm {
+    stats: &'static GunStats,
+    time_left: f32,
+    magazine: Magazine,
+    recoil: Recoil,
 }
 
-impl Gun for Pistol {
-    fn new() -> Box<Self>
-    where
-        Self: Sized,
-    {
-        Box::new(Pistol {
+impl Firearm {
+    pub fn new(stats: &'static GunStats) -> Box<Self> {
+        Box::new(Firearm {
+            stats,
             time_left: 0.0,
-            mag_size: 7,
-            reloading: false,
+            magazine: Magazine::new(stats.magazine, stats.reserve, stats.reload),
+            recoil: stats.recoil.clone(),
         })
     }
+}
 
+impl Gun for Firearm {
     fn name(&self) -> &str {
-        "Pistol"
+        self.stats.name
     }
 
     fn shoot(
         &mut self,
-        time: Res<Time>,
-        mouse: Res<Input<MouseButton>>,
-        player_transform: &Transform,
-        angle: Rad<f32>,
-        materials: Res<crate::Materials>,
-        mut commands: Commands,
-    ) {
+        time: &Time,
+        trigger: bool,
+        aim: Aim,
+        materials: &crate::Materials,
+        commands: &mut Commands,
+    ) -> bool {
         self.time_left -= time.delta_seconds();
-        if self.time_left <= 0.0 {
-            self.reloading = false;
-            if mouse.just_pressed(MouseButton::Left) {
-                let velocity = crate::Vel(Vec2::new(angle.cos() * 6.0, angle.sin() * 6.0));
-
-                // #[allow(clippy::clone_on_copy)]
-                let mut transform = *player_transform;
-                transform.translation.z = 0.0;
-                transform.rotate(Quat::from_rotation_z(angle.0));
-
-                commands
-                    .spawn()
-                    .insert_bundle(SpriteBundle {
-                        sprite: Sprite::new(Vec2::new(10.0, 4.0)),
-                        material: materials.bullet.clone(),
-                        transform,
-                        ..Default::default()
-                    })
-                    .insert(crate::Bullet {})
-                    .insert(velocity);
-
-                self.mag_size -= 1;
-
-                // Set time before next possible shot
-                if self.mag_size == 0 {
-                    self.reload();
-                } else {
-                    self.time_left = 0.01;
+        self.magazine.tick(time.delta_seconds());
+        if self.time_left <= 0.0 && trigger && self.magazine.take_round() {
+            let mut random = rand::thread_rng();
+            let ballistics = self.stats.ballistics.boosted(aim.damage_factor);
+            match self.stats.projectile {
+                Projectile::Bullets { pellets } => {
+                    for _index in 0..pellets {
+                        let pellet_angle = aim.angle.0 + self.recoil.deviation(&mut random);
+                        spawn_bullet(commands, materials, aim.muzzle, pellet_angle, ballistics);
+                    }
+                }
+                Projectile::Hitscan => {
+                    let shot_angle = aim.angle.0 + self.recoil.deviation(&mut random);
+                    commands
+                        .spawn()
+                        .insert(crate::hitscan::HitscanShot::new(aim.muzzle, shot_angle, ballistics));
                 }
             }
+
+            self.recoil.fire();
+
+            // Set time before next possible shot
+            self.time_left = self.stats.fire_interval;
+            return true;
         }
+        false
     }
 
-    fn left_in_mag(&self) -> u16 {
-        self.mag_size
+    fn automatic(&self) -> bool {
+        self.stats.automatic
     }
 
-    fn reload(&mut self) {
-        self.time_left = 0.8;
-        self.mag_size = 7;
-        self.reloading = true;
+    fn loudness(&self) -> f32 {
+        self.stats.loudness
     }
 
-    fn reloading(&self) -> bool {
-        self.reloading
+    fn recoil(&self) -> &Recoil {
+        &self.recoil
+    }
+
+    fn recoil_mut(&mut self) -> &mut Recoil {
+        &mut self.recoil
+    }
+
+    fn magazine(&self) -> &Magazine {
+        &self.magazine
+    }
+
+    fn magazine_mut(&mut self) -> &mut Magazine {
+        &mut self.magazine
+    }
+
+    fn icon(&self) -> &str {
+        self.stats.icon
+    }
+
+    fn icon_angle(&self) -> f32 {
+        self.stats.icon_angle
+    }
+
+    fn held_size(&self) -> Vec2 {
+        Vec2::splat(self.stats.held_size)
     }
 }
 // <a target="_blank" href="https://icons8.com/icon/35235/center-of-gravity">Center of Gravity</a> icon by <a target="_blank" href="https://icons8.com">Icons8</a>
@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use cgmath::{Angle, Rad};
+
+use crate::gun::Gun;
+use crate::hud::GunIcons;
+use crate::Player;
+
+/// How far below the player's centre they hold their gun
+static HAND_DROP: f32 = 6.0;
+/// Depth above or below the player the gun is drawn at
+static GUN_Z: f32 = 0.01;
+
+/// Sprite of the gun the player is holding, remembering which image it shows
+pub struct HeldGun(Option<String>);
+
+/// Where shots leave a gun held by a player at `player_location` aiming at `angle`
+pub fn muzzle_location(player_location: Vec2, angle: Rad<f32>, gun: &dyn Gun) -> Vec2 {
+    player_location + hand_offset() + Vec2::new(angle.cos(), angle.sin()) * gun.held_size().x
+}
+
+fn hand_offset() -> Vec2 {
+    Vec2::new(0.0, -HAND_DROP)
+}
+
+pub fn update_held_gun(
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut icons: ResMut<GunIcons>,
+    player_query: Query<(&Player, &Rad<f32>, &Transform, &Children)>,
+    mut gun_query: Query<
+        (&mut HeldGun, &mut Transform, &mut Sprite, &mut Handle<ColorMaterial>, &mut Visible),
+        Without<Player>,
+    >,
+) {
+    for (player, angle, player_trans, children) in player_query.iter() {
+        for child in children.iter() {
+            let (mut held, mut trans, mut sprite, mut material, mut visible) = match gun_query.get_mut(*child) {
+                Ok(held) => held,
+                Err(_) => continue,
+            };
+
+            let gun = match &player.gun {
+                Some(gun) => gun,
+                None => {
+                    visible.is_visible = false;
+                    held.0 = None;
+                    continue;
+                }
+            };
+
+            if held.0.as_deref() != Some(gun.icon()) {
+                *material = icons.material(gun.icon(), &asset_server, &mut materials);
+                sprite.size = gun.held_size();
+                held.0 = Some(gun.icon().to_string());
+            }
+            visible.is_visible = true;
+
+            // Undo the player's scale so the gun keeps its own size and offset
+            let parent_scale = player_trans.scale;
+            let offset = hand_offset() + Vec2::new(angle.cos(), angle.sin()) * gun.held_size().x / 2.0;
+            // Tuck the gun behind the player when aiming away from the camera
+            let z = if angle.sin() > 0.5 { -GUN_Z } else { GUN_Z };
+
+            // Mirror the image when aiming the opposite way to where it points,
+            // keeping the gun the right way up, then turn its barrel onto the aim
+            let icon_angle = gun.icon_angle();
+            let flip = (angle.cos() < 0.0) != (icon_angle.cos() < 0.0);
+            let facing = if flip { -icon_angle } else { icon_angle };
+
+            trans.translation = offset.extend(z) / parent_scale;
+            trans.rotation = Quat::from_rotation_z(angle.0 - facing);
+            trans.scale = Vec3::ONE / parent_scale;
+            sprite.flip_y = flip;
+        }
+    }
+}
+
+/// Adds the gun sprite as a child of the player
+pub fn spawn_held_gun(parent: &mut ChildBuilder) {
+    parent
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite::new(Vec2::ZERO),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(HeldGun(None));
+}
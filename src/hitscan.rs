@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+
+use crate::events::{ZomHit, ZomKilled};
+use crate::gun::Ballistics;
+use crate::wall::{self, Wall};
+use crate::{Health, HitReaction, Materials, Zom};
+
+use std::cmp::Ordering;
+
+/// How far a hitscan shot reaches, in pixels
+static HITSCAN_RANGE: f32 = 1500.0;
+static TRACER_SECONDS: f32 = 0.08;
+static TRACER_WIDTH: f32 = 2.0;
+static TRACER_Z: f32 = 0.2;
+
+/// A shot that hits everything along its line at once, resolved on the next update
+pub struct HitscanShot {
+    origin: Vec2,
+    direction: Vec2,
+    ballistics: Ballistics,
+}
+
+impl HitscanShot {
+    pub fn new(origin: Vec2, angle: f32, ballistics: Ballistics) -> Self {
+        HitscanShot {
+            origin,
+            direction: Vec2::new(angle.cos(), angle.sin()),
+            ballistics,
+        }
+    }
+
+    /// Distance along the shot at which it enters a circle, if it does
+    fn enters_circle(&self, center: Vec2, radius: f32) -> Option<f32> {
+        let along = (center - self.origin).dot(self.direction);
+        let off_line_squared = (center - self.origin).length_squared() - along.powi(2);
+        if along < 0.0 || off_line_squared > radius.powi(2) {
+            return None;
+        }
+
+        Some((along - (radius.powi(2) - off_line_squared).sqrt()).max(0.0))
+    }
+}
+
+/// Line left behind by a hitscan shot
+pub struct Tracer(Timer);
+
+pub fn resolve_hitscan(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    shot_query: Query<(&HitscanShot, Entity)>,
+    wall_query: Query<(&Wall, &Transform)>,
+    mut zom_query: Query<(&Zom, &Transform, &mut Health, &mut HitReaction, Entity)>,
+    mut hit_writer: EventWriter<ZomHit>,
+    mut killed_writer: EventWriter<ZomKilled>,
+) {
+    for (shot, shot_entity) in shot_query.iter() {
+        commands.entity(shot_entity).despawn();
+
+        // Walls stop the shot outright
+        let mut range = HITSCAN_RANGE;
+        let end = shot.origin + shot.direction * range;
+        if let Some((fraction, _normal)) = wall::first_hit(&wall_query, shot.origin, end) {
+            range *= fraction;
+        }
+
+        let mut hits: Vec<(f32, Entity)> = zom_query
+            .iter_mut()
+            .filter(|(_, _, health, _, _)| !health.is_dead())
+            .filter_map(|(zom, trans, _, _, entity)| {
+                let (size, _mass) = zom.zom_type.size_and_mass();
+                shot.enters_circle(trans.translation.truncate(), size)
+                    .filter(|dist| *dist <= range)
+                    .map(|dist| (dist, entity))
+            })
+            .collect();
+        hits.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let mut penetration_factor = 1.0;
+        for (index, (dist, entity)) in hits.iter().enumerate() {
+            if let Ok((zom, zom_trans, mut health, mut reaction, _)) = zom_query.get_mut(*entity) {
+                let (_size, mass) = zom.zom_type.size_and_mass();
+                let damage = shot.ballistics.damage_at(*dist) * penetration_factor;
+                health.damage(damage);
+                reaction.hit(shot.direction * shot.ballistics.knockback / mass);
+
+                hit_writer.send(ZomHit {
+                    zom_type: zom.zom_type,
+                    location: shot.origin + shot.direction * *dist,
+                    direction: shot.direction,
+                    damage,
+                });
+
+                if health.is_dead() {
+                    killed_writer.send(ZomKilled {
+                        zom: *entity,
+                        zom_type: zom.zom_type,
+                        location: zom_trans.translation.truncate(),
+                    });
+                }
+            }
+
+            if index as u32 >= shot.ballistics.penetration {
+                range = *dist;
+                break;
+            }
+            penetration_factor *= shot.ballistics.penetration_damage;
+        }
+
+        let mut transform = Transform::from_translation((shot.origin + shot.direction * range / 2.0).extend(TRACER_Z));
+        transform.rotate(Quat::from_rotation_z(shot.direction.y.atan2(shot.direction.x)));
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite::new(Vec2::new(range, TRACER_WIDTH)),
+                material: materials.tracer.clone(),
+                transform,
+                ..Default::default()
+            })
+            .insert(Tracer(Timer::from_seconds(TRACER_SECONDS, false)));
+    }
+}
+
+/// Thins tracers out and removes them
+pub fn fade_tracers(mut commands: Commands, time: Res<Time>, mut tracer_query: Query<(&mut Tracer, &mut Transform, Entity)>) {
+    for (mut tracer, mut trans, entity) in tracer_query.iter_mut() {
+        tracer.0.tick(time.delta());
+        trans.scale.y = 1.0 - tracer.0.percent();
+
+        if tracer.0.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
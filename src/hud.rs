@@ -0,0 +1,467 @@
+use bevy::prelude::*;
+
+use crate::coop::{self, Downed};
+use crate::pickup::{Buff, Buffs};
+use crate::score::Score;
+use crate::shop::{Armour, Cash};
+use crate::stamina::Stamina;
+use crate::wave::Wave;
+use crate::{Health, Player};
+
+use std::collections::HashMap;
+
+static FONT_PATH: &str = "fonts/FiraMono-Medium.ttf";
+static BAR_WIDTH: f32 = 160.0;
+static ICON_SIZE: f32 = 48.0;
+
+/// Row along the bottom of the screen holding each player's panel
+pub struct PlayerPanels;
+
+/// The HUD panel belonging to a player, every part of it remembers whose it is
+pub struct PlayerPanel(Entity);
+
+pub struct HealthBar(Entity);
+
+pub struct HealthText(Entity);
+
+pub struct StaminaBar(Entity);
+
+pub struct AmmoText(Entity);
+
+pub struct GrenadeText(Entity);
+
+/// Icon of a player's equipped gun, remembering which image it is showing
+pub struct WeaponIcon {
+    player: Entity,
+    showing: Option<String>,
+}
+
+/// One material per gun image, reused whenever that gun is shown again
+#[derive(Default)]
+pub struct GunIcons(HashMap<String, Handle<ColorMaterial>>);
+
+impl GunIcons {
+    pub fn material(
+        &mut self,
+        icon: &str,
+        asset_server: &AssetServer,
+        materials: &mut Assets<ColorMaterial>,
+    ) -> Handle<ColorMaterial> {
+        self.0
+            .entry(icon.to_string())
+            .or_insert_with(|| materials.add(asset_server.load(icon).into()))
+            .clone()
+    }
+}
+
+pub struct ReloadFrame(Entity);
+
+pub struct ReloadBar(Entity);
+
+/// Badge shown while one of a player's timed buffs is running
+pub struct BuffIndicator {
+    player: Entity,
+    buff: Buff,
+}
+
+pub struct BuffTime {
+    player: Entity,
+    buff: Buff,
+}
+
+pub struct WaveText;
+
+pub struct ScoreText;
+
+pub struct CashText;
+
+/// Adds a panel for each player who joins and clears away panels of players who are gone
+pub fn update_player_panels(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    new_player_query: Query<(&Player, Entity), Added<Player>>,
+    player_query: Query<&Player>,
+    panel_query: Query<(&PlayerPanel, Entity)>,
+    panels_query: Query<Entity, With<PlayerPanels>>,
+) {
+    for (panel, entity) in panel_query.iter() {
+        if player_query.get(panel.0).is_err() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    let panels = match panels_query.single() {
+        Ok(panels) => panels,
+        Err(_) => return,
+    };
+    for (player, entity) in new_player_query.iter() {
+        commands.entity(panels).with_children(|parent| {
+            spawn_player_panel(parent, &asset_server, &mut materials, player.number, entity);
+        });
+    }
+}
+
+pub fn update_health_hud(
+    player_query: Query<(&Health, &Armour, Option<&Downed>), With<Player>>,
+    mut bar_query: Query<(&HealthBar, &mut Style)>,
+    mut text_query: Query<(&HealthText, &mut Text)>,
+) {
+    // A downed player's bar fills back up as they get revived
+    for (bar, mut bar_style) in bar_query.iter_mut() {
+        if let Ok((health, _, downed)) = player_query.get(bar.0) {
+            let fraction = match downed {
+                Some(downed) => downed.revive_progress(),
+                None => health.fraction(),
+            };
+            bar_style.size.width = Val::Percent(fraction * 100.0);
+        }
+    }
+    for (health_text, mut text) in text_query.iter_mut() {
+        if let Ok((health, armour, downed)) = player_query.get(health_text.0) {
+            text.sections[0].value = match (downed, armour.points() > 0.0) {
+                (Some(_), _) => "DOWN".to_string(),
+                (None, true) => format!("{}/{} +{}", health.current.ceil(), health.max, armour.points().ceil()),
+                (None, false) => format!("{}/{}", health.current.ceil(), health.max),
+            };
+        }
+    }
+}
+
+pub fn update_stamina_hud(
+    player_query: Query<&Stamina, With<Player>>,
+    mut bar_query: Query<(&StaminaBar, &mut Style)>,
+) {
+    for (bar, mut bar_style) in bar_query.iter_mut() {
+        if let Ok(stamina) = player_query.get(bar.0) {
+            bar_style.size.width = Val::Percent(stamina.fraction() * 100.0);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_weapon_hud(
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut icons: ResMut<GunIcons>,
+    player_query: Query<&Player>,
+    mut text_query: Query<(&AmmoText, &mut Text), Without<GrenadeText>>,
+    mut grenade_query: Query<(&GrenadeText, &mut Text), Without<AmmoText>>,
+    mut icon_query: Query<(&mut WeaponIcon, &mut Handle<ColorMaterial>, &mut Visible)>,
+    mut frame_query: Query<(&ReloadFrame, &mut Style), Without<ReloadBar>>,
+    mut bar_query: Query<(&ReloadBar, &mut Style), Without<ReloadFrame>>,
+) {
+    for (ammo, mut text) in text_query.iter_mut() {
+        if let Ok(player) = player_query.get(ammo.0) {
+            text.sections[0].value = match &player.gun {
+                Some(gun) => format!("{}  {} / {}", gun.name(), gun.left_in_mag(), gun.reserve()),
+                None => "No gun".to_string(),
+            };
+        }
+    }
+    for (grenades, mut text) in grenade_query.iter_mut() {
+        if let Ok(player) = player_query.get(grenades.0) {
+            text.sections[0].value = format!("Grenades: {}", player.grenades);
+        }
+    }
+
+    for (mut icon, mut material, mut visible) in icon_query.iter_mut() {
+        let player = match player_query.get(icon.player) {
+            Ok(player) => player,
+            Err(_) => continue,
+        };
+
+        let wanted = player.gun.as_ref().map(|gun| gun.icon().to_string());
+        if icon.showing != wanted {
+            if let Some(path) = &wanted {
+                *material = icons.material(path, &asset_server, &mut materials);
+            }
+            visible.is_visible = wanted.is_some();
+            icon.showing = wanted;
+        }
+    }
+
+    for (frame, mut frame_style) in frame_query.iter_mut() {
+        let progress = player_query
+            .get(frame.0)
+            .ok()
+            .and_then(|player| player.gun.as_ref())
+            .and_then(|gun| gun.reload_progress());
+        frame_style.display = match progress {
+            Some(_) => Display::Flex,
+            None => Display::None,
+        };
+    }
+    for (bar, mut bar_style) in bar_query.iter_mut() {
+        let progress = player_query
+            .get(bar.0)
+            .ok()
+            .and_then(|player| player.gun.as_ref())
+            .and_then(|gun| gun.reload_progress());
+        if let Some(progress) = progress {
+            bar_style.size.width = Val::Percent(progress * 100.0);
+        }
+    }
+}
+
+pub fn update_buff_hud(
+    player_query: Query<&Buffs, With<Player>>,
+    mut indicator_query: Query<(&BuffIndicator, &mut Style)>,
+    mut time_query: Query<(&BuffTime, &mut Text)>,
+) {
+    for (indicator, mut style) in indicator_query.iter_mut() {
+        let active = player_query
+            .get(indicator.player)
+            .map(|buffs| buffs.active(indicator.buff))
+            .unwrap_or(false);
+        style.display = match active {
+            true => Display::Flex,
+            false => Display::None,
+        };
+    }
+    for (time, mut text) in time_query.iter_mut() {
+        let left = player_query
+            .get(time.player)
+            .ok()
+            .and_then(|buffs| buffs.seconds_left(time.buff));
+        if let Some(left) = left {
+            text.sections[0].value = format!("{} {}", time.buff.name(), left.ceil());
+        }
+    }
+}
+
+pub fn update_progress_hud(
+    wave: Res<Wave>,
+    score: Res<Score>,
+    cash: Res<Cash>,
+    mut wave_query: Query<&mut Text, (With<WaveText>, Without<ScoreText>, Without<CashText>)>,
+    mut score_query: Query<&mut Text, (With<ScoreText>, Without<WaveText>, Without<CashText>)>,
+    mut cash_query: Query<&mut Text, (With<CashText>, Without<WaveText>, Without<ScoreText>)>,
+) {
+    if let Ok(mut text) = wave_query.single_mut() {
+        text.sections[0].value = format!("Wave {}", wave.number);
+    }
+    if let Ok(mut text) = score_query.single_mut() {
+        text.sections[0].value = match score.multiplier() {
+            1 => format!("Score: {}", score.points),
+            multiplier => format!("Score: {}  x{}", score.points, multiplier),
+        };
+    }
+    if let Ok(mut text) = cash_query.single_mut() {
+        text.sections[0].value = format!("Cash: ${}", cash.0);
+    }
+}
+
+fn hud_text(asset_server: &AssetServer, font_size: f32) -> TextBundle {
+    TextBundle {
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font: asset_server.load(FONT_PATH),
+                font_size,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..Default::default()
+    }
+}
+
+fn bar_frame(materials: &mut Assets<ColorMaterial>, height: f32) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            size: Size::new(Val::Px(BAR_WIDTH), Val::Px(height)),
+            padding: Rect::all(Val::Px(2.0)),
+            ..Default::default()
+        },
+        material: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+        ..Default::default()
+    }
+}
+
+fn bar_fill(materials: &mut Assets<ColorMaterial>, color: Color) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            ..Default::default()
+        },
+        material: materials.add(color.into()),
+        ..Default::default()
+    }
+}
+
+/// A row holding its children side by side, centred vertically
+fn hud_row(materials: &mut Assets<ColorMaterial>) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        material: materials.add(Color::NONE.into()),
+        ..Default::default()
+    }
+}
+
+/// Health, stamina, weapon, grenades, buffs and reload progress for one player, top to bottom
+fn spawn_player_panel(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    materials: &mut Assets<ColorMaterial>,
+    number: usize,
+    player: Entity,
+) {
+    let mut label = hud_text(asset_server, 18.0);
+    label.text.sections[0].value = format!("P{}", number + 1);
+    label.text.sections[0].style.color = coop::player_color(number);
+
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexStart,
+                margin: Rect {
+                    right: Val::Px(15.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .insert(PlayerPanel(player))
+        .with_children(|parent| {
+            parent.spawn_bundle(label);
+
+            parent.spawn_bundle(hud_row(materials)).with_children(|parent| {
+                parent
+                    .spawn_bundle(bar_frame(materials, 20.0))
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(bar_fill(materials, Color::rgb(0.2, 0.8, 0.2)))
+                            .insert(HealthBar(player));
+                    });
+                parent
+                    .spawn_bundle(hud_text(asset_server, 18.0))
+                    .insert(HealthText(player));
+            });
+
+            parent
+                .spawn_bundle(bar_frame(materials, 8.0))
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(bar_fill(materials, Color::rgb(0.3, 0.6, 0.9)))
+                        .insert(StaminaBar(player));
+                });
+
+            parent.spawn_bundle(hud_row(materials)).with_children(|parent| {
+                parent
+                    .spawn_bundle(ImageBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(ICON_SIZE), Val::Px(ICON_SIZE)),
+                            ..Default::default()
+                        },
+                        visible: Visible {
+                            is_visible: false,
+                            is_transparent: true,
+                        },
+                        ..Default::default()
+                    })
+                    .insert(WeaponIcon { player, showing: None });
+                parent
+                    .spawn_bundle(hud_text(asset_server, 20.0))
+                    .insert(AmmoText(player));
+            });
+
+            parent
+                .spawn_bundle(hud_text(asset_server, 16.0))
+                .insert(GrenadeText(player));
+
+            parent.spawn_bundle(hud_row(materials)).with_children(|parent| {
+                for buff in Buff::ALL.iter() {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                display: Display::None,
+                                padding: Rect::all(Val::Px(2.0)),
+                                margin: Rect {
+                                    right: Val::Px(4.0),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                            material: materials.add(buff.color().into()),
+                            ..Default::default()
+                        })
+                        .insert(BuffIndicator { player, buff: *buff })
+                        .with_children(|parent| {
+                            parent
+                                .spawn_bundle(hud_text(asset_server, 14.0))
+                                .insert(BuffTime { player, buff: *buff });
+                        });
+                }
+            });
+
+            let mut frame = bar_frame(materials, 10.0);
+            frame.style.display = Display::None;
+            parent
+                .spawn_bundle(frame)
+                .insert(ReloadFrame(player))
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(bar_fill(materials, Color::rgb(0.9, 0.8, 0.3)))
+                        .insert(ReloadBar(player));
+                });
+        });
+}
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn load_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let transparent = materials.add(Color::NONE.into());
+
+    // Player panels, along the bottom from the left
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    ..Default::default()
+                },
+                align_items: AlignItems::FlexEnd,
+                ..Default::default()
+            },
+            material: transparent.clone(),
+            ..Default::default()
+        })
+        .insert(PlayerPanels);
+
+    // Wave, score and cash, top right
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(5.0),
+                    right: Val::Px(5.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::FlexEnd,
+                ..Default::default()
+            },
+            material: transparent,
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(hud_text(&asset_server, 20.0)).insert(WaveText);
+            parent.spawn_bundle(hud_text(&asset_server, 20.0)).insert(ScoreText);
+            parent.spawn_bundle(hud_text(&asset_server, 20.0)).insert(CashText);
+        });
+}
+// -----------------------------------
@@ -1,17 +1,72 @@
 use bevy::{ecs::query::WorldQuery, math::f32, prelude::*};
+use animation::{CharacterAnimation, SpriteSheets};
+use bindings::{Action, PlayerActions};
+use coop::Downed;
+use gamepad::{GamepadInput, InputDevice};
 use cgmath::{Angle, Rad};
+use events::{PlayerHurt, ShotFired, ZomHit, ZomKilled};
 use gun::Gun;
+use particles::EmitParticles;
+use perception::{Noise, Perception};
+use pickup::{Buff, Buffs};
+use shop::Armour;
 use rand::{self, Rng};
-
+use stamina::{Dash, Stamina};
+
+use std::collections::HashMap;
+
+mod animation;
+mod bindings;
+mod boss;
+mod coop;
+mod crosshair;
+mod death;
+mod events;
+mod gamepad;
+mod grenade;
 mod gun;
+mod gun_sprite;
+mod hitscan;
+mod hud;
+mod melee;
+mod net;
+mod particles;
+mod perception;
+mod pickup;
+mod score;
+mod settings;
+mod shop;
+mod stamina;
+mod wall;
+mod wave;
 
 static MOVE_SPEED: f32 = 1.8;
+static SPRINT_FACTOR: f32 = 1.6;
+static SPRINT_STAMINA_PER_SECOND: f32 = 25.0;
+static DASH_SPEED: f32 = 7.0;
+static DASH_STAMINA: f32 = 30.0;
+/// Movement speed while reloading, as a fraction of normal
+static RELOAD_MOVE_FACTOR: f32 = 0.6;
 static ZOM_SPEED: f32 = 2.2;
 static ZOM_SIZE: f32 = 10.0;
+static ZOM_HEALTH: f32 = 10.0;
 static STRONG_ZOM_SIZE: f32 = 15.0;
 static STRONG_ZOM_SPEED: f32 = 1.6;
+/// How much further than its size a zombie can reach to bite
+static BITE_REACH: f32 = 12.0;
+static BITE_SECONDS: f32 = 1.0;
+static ZOM_BITE_DAMAGE: f32 = 5.0;
+static STRONG_ZOM_BITE_DAMAGE: f32 = 10.0;
+static BOSS_SIZE: f32 = 40.0;
+static BOSS_SPEED: f32 = 1.2;
+static BOSS_HEALTH: f32 = 600.0;
+static PLAYER_HEALTH: f32 = 100.0;
+/// How far a player is shoved away from whatever hurt them
+static HURT_PUSH: f32 = 12.0;
+static HIT_STUN_SECONDS: f32 = 0.15;
+static KNOCKBACK_DECAY: f32 = 0.8;
+static CAMERA_KICK_DECAY: f32 = 0.8;
 
-type PeopleBorrow<'a> = (&'a Player, &'a Transform);
 type ZomBorrowTransMut<'a> = (&'a Zom, &'a mut Transform);
 
 // impl WorldQuery for Rad<f32> {
@@ -20,13 +75,53 @@ type ZomBorrowTransMut<'a> = (&'a Zom, &'a mut Transform);
 //     type State;
 // }
 
-struct Player {
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GameState {
+    Playing,
+    Settings,
+    /// Spending cash between waves
+    Shop,
+}
+
+pub struct Player {
+    /// 0 for the first player, used for their HUD panel and colour
+    number: usize,
     gun: Option<Box<dyn gun::Gun>>,
+    /// Guns carried but not in hand, cycled through with Q
+    spare_guns: Vec<Box<dyn gun::Gun>>,
+    grenades: u32,
+}
+
+impl Player {
+    /// Puts the held gun at the back and takes out the next one
+    fn cycle_gun(&mut self) {
+        if self.spare_guns.is_empty() {
+            return;
+        }
+
+        let next = self.spare_guns.remove(0);
+        if let Some(held) = self.gun.replace(next) {
+            self.spare_guns.push(held);
+        }
+    }
 }
 
-enum ZomType {
+#[derive(Clone, Copy, PartialEq)]
+pub enum ZomType {
     Default,
     Strong,
+    Boss,
+}
+
+impl ZomType {
+    /// Hit radius and how hard it is to knock back
+    fn size_and_mass(&self) -> (f32, f32) {
+        match self {
+            ZomType::Default => (ZOM_SIZE, 1.0),
+            ZomType::Strong => (STRONG_ZOM_SIZE, 2.5),
+            ZomType::Boss => (BOSS_SIZE, 10.0),
+        }
+    }
 }
 
 impl Default for ZomType {
@@ -36,8 +131,70 @@ impl Default for ZomType {
 }
 
 #[derive(Default)]
-struct Zom {
+pub struct Zom {
     zom_type: ZomType,
+    /// Time until the zombie can bite again
+    bite_cooldown: Timer,
+}
+
+/// Push and stun applied to a zombie when it gets hit
+pub struct HitReaction {
+    velocity: Vec2,
+    stun: Timer,
+}
+
+impl Default for HitReaction {
+    fn default() -> Self {
+        let mut stun = Timer::from_seconds(HIT_STUN_SECONDS, false);
+        stun.tick(stun.duration());
+
+        HitReaction {
+            velocity: Vec2::ZERO,
+            stun,
+        }
+    }
+}
+
+impl HitReaction {
+    fn hit(&mut self, push: Vec2) {
+        self.velocity += push;
+        self.stun.reset();
+    }
+
+    fn stunned(&self) -> bool {
+        !self.stun.finished()
+    }
+}
+
+pub struct Health {
+    current: f32,
+    max: f32,
+}
+
+impl Health {
+    fn new(max: f32) -> Self {
+        Health { current: max, max }
+    }
+
+    fn damage(&mut self, amount: f32) {
+        self.current = (self.current - amount).max(0.0);
+    }
+
+    fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    fn restore(&mut self) {
+        self.current = self.max;
+    }
+
+    fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    fn fraction(&self) -> f32 {
+        self.current / self.max
+    }
 }
 
 struct Vel(Vec2);
@@ -80,13 +237,57 @@ impl Velocity for Vec2 {
     }
 }
 
-struct Bullet {}
+struct Bullet {
+    ballistics: gun::Ballistics,
+    /// Damage multiplier left after passing through zombies
+    penetration_factor: f32,
+    pierces_left: u32,
+    ricochets_left: u32,
+    travelled: f32,
+    /// Zombies already hit, so one can't be hit twice while the bullet passes through
+    hit: Vec<Entity>,
+}
+
+impl Bullet {
+    fn new(ballistics: gun::Ballistics) -> Self {
+        Bullet {
+            ballistics,
+            penetration_factor: 1.0,
+            pierces_left: ballistics.penetration,
+            ricochets_left: ballistics.ricochets,
+            travelled: 0.0,
+            hit: Vec::new(),
+        }
+    }
+
+    fn damage(&self) -> f32 {
+        self.ballistics.damage_at(self.travelled) * self.penetration_factor
+    }
+
+    /// Records a hit, returns whether the bullet carries on through
+    fn pierce(&mut self, zom: Entity) -> bool {
+        self.hit.push(zom);
+        if self.pierces_left == 0 {
+            return false;
+        }
+
+        self.pierces_left -= 1;
+        self.penetration_factor *= self.ballistics.penetration_damage;
+        true
+    }
+}
+
+struct MainCamera;
 
 pub struct Materials {
     bullet: Handle<ColorMaterial>,
     zom: Handle<ColorMaterial>,
-    strong_zom: Handle<ColorMaterial>,
-    zom_sprite: Handle<ColorMaterial>,
+    blood: Handle<ColorMaterial>,
+    slam_marker: Handle<ColorMaterial>,
+    reward: Handle<ColorMaterial>,
+    tracer: Handle<ColorMaterial>,
+    grenade: Handle<ColorMaterial>,
+    pickup: HashMap<pickup::PickupKind, Handle<ColorMaterial>>,
 }
 
 trait ClampMax {
@@ -111,255 +312,530 @@ fn main() {
 
     app.add_plugins(DefaultPlugins);
 
+    let net_role = net::NetRole::from_args();
+    app.insert_resource(net_role);
+    app.add_startup_system(net::start_networking.system());
+
+    app.add_startup_system_to_stage(StartupStage::PreStartup, animation::load_sprite_sheets.system());
     app.add_startup_system(load_materials.system());
 
     app.add_startup_system(load_player.system());
     app.add_startup_system(load_camera.system());
-    app.add_startup_system(load_text.system());
-
-    app.add_system(face_mouse.system());
-    app.add_system(shoot_bullet.system());
-    app.add_system(move_elements.system());
-    app.add_system(move_player.system());
-    app.add_system(player_input.system());
-    app.add_system(spawn_zom.system());
-    app.add_system(move_zom.system());
-    app.add_system(zom_bullet_collision.system());
-    app.add_system(despawn_bullet.system());
-    app.add_system(change_sprite.system());
-    app.add_system(update_text.system());
+    app.add_startup_system(hud::load_hud.system());
+    app.add_startup_system(score::load_high_scores.system());
+    app.add_startup_system(settings::load_settings.system());
+    app.add_startup_system(bindings::load_bindings.system());
+    app.add_startup_system(shop::load_shop.system());
+    app.add_startup_system(wave::load_waves.system());
+    app.insert_resource(gamepad::ConnectedGamepads::default());
+    app.add_startup_system(boss::load_boss_hud.system());
+    app.add_startup_system(wall::load_walls.system());
+    app.add_startup_system(particles::load_particle_pool.system());
+
+    app.insert_resource(death::DeathConfig::default());
+    app.init_resource::<death::BloodDecals>();
+    app.init_resource::<score::Score>();
+    app.init_resource::<hud::GunIcons>();
+
+    app.add_event::<Noise>();
+    app.add_event::<EmitParticles>();
+    app.add_event::<ShotFired>();
+    app.add_event::<ZomHit>();
+    app.add_event::<ZomKilled>();
+    app.add_event::<PlayerHurt>();
+
+    app.add_state(GameState::Playing);
+
+    // Clients only move their own player ahead of the server and draw everything else from snapshots
+    if net_role.is_client() {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(face_mouse.system())
+                .with_system(move_player.system())
+                .with_system(wall::collide_walls.system())
+                .with_system(net::interpolate_proxies.system())
+                .with_system(animation::animate_sprites.system())
+                .with_system(gun_sprite::update_held_gun.system()),
+        );
+    } else {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(face_mouse.system())
+                .with_system(shoot_bullet.system())
+                .with_system(settle_recoil.system())
+                .with_system(kick_camera.system())
+                .with_system(move_elements.system())
+                .with_system(move_player.system())
+                .with_system(player_input.system())
+                .with_system(spawn_zom.system())
+                .with_system(perception::hear_shots.system())
+                .with_system(perception::update_perception.system())
+                .with_system(move_zom.system())
+                .with_system(apply_knockback.system())
+                .with_system(wall::collide_walls.system())
+                .with_system(bullet_travel.system())
+                .with_system(zom_bullet_collision.system())
+                .with_system(despawn_bullet.system())
+                .with_system(hitscan::resolve_hitscan.system())
+                .with_system(hitscan::fade_tracers.system())
+                .with_system(grenade::throw_grenade.system())
+                .with_system(grenade::update_grenades.system())
+                .with_system(melee::swing_melee.system())
+                .with_system(animation::animate_sprites.system())
+                .with_system(gun_sprite::update_held_gun.system())
+                .with_system(bite_players.system())
+                .with_system(hurt_player.system())
+                .with_system(coop::join_players.system())
+                .with_system(coop::down_players.system())
+                .with_system(coop::revive_players.system())
+                .with_system(death::bleed.system())
+                .with_system(death::kill_zoms.system())
+                .with_system(death::start_death_animation.system())
+                .with_system(death::update_dying.system())
+                .with_system(death::fade_corpses.system())
+                .with_system(particles::combat_particles.system())
+                .with_system(particles::emit_particles.system())
+                .with_system(particles::update_particles.system())
+                .with_system(score::score_kills.system())
+                .with_system(score::tally_damage.system())
+                .with_system(score::game_over.system())
+                .with_system(wave::advance_wave.system())
+                .with_system(boss::spawn_boss.system())
+                .with_system(boss::boss_attack.system())
+                .with_system(boss::fade_slam_markers.system())
+                .with_system(boss::drop_rewards.system())
+                .with_system(boss::collect_reward.system())
+                .with_system(pickup::drop_pickups.system())
+                .with_system(pickup::expire_pickups.system())
+                .with_system(pickup::collect_pickups.system())
+                .with_system(pickup::update_buffs.system())
+                .with_system(shop::earn_cash.system()),
+        );
+    }
+    app.add_system_set(SystemSet::on_enter(GameState::Settings).with_system(settings::open_settings_menu.system()));
+    app.add_system_set(
+        SystemSet::on_update(GameState::Settings)
+            .with_system(settings::settings_buttons.system())
+            .with_system(settings::capture_rebinding.system()),
+    );
+    app.add_system_set(SystemSet::on_exit(GameState::Settings).with_system(settings::close_settings_menu.system()));
+    app.add_system_set(SystemSet::on_enter(GameState::Shop).with_system(shop::open_shop.system()));
+    app.add_system_set(SystemSet::on_update(GameState::Shop).with_system(shop::shop_buttons.system()));
+    app.add_system_set(SystemSet::on_exit(GameState::Shop).with_system(shop::close_shop.system()));
+
+    app.add_system(settings::toggle_settings.system());
+    app.add_system_to_stage(
+        CoreStage::PreUpdate,
+        gamepad::update_gamepads
+            .system()
+            .label("update_gamepad")
+            .after(bevy::input::InputSystem),
+    );
+    app.add_system_to_stage(
+        CoreStage::PreUpdate,
+        net::server_receive
+            .system()
+            .label("net_receive")
+            .after(bevy::input::InputSystem),
+    );
+    app.add_system_to_stage(
+        CoreStage::PreUpdate,
+        bindings::update_actions
+            .system()
+            .after("update_gamepad")
+            .after("net_receive"),
+    );
+    app.add_system_to_stage(CoreStage::PreUpdate, net::client_receive.system());
+    app.add_system_to_stage(CoreStage::PostUpdate, net::client_send_input.system());
+    app.add_system_to_stage(CoreStage::PostUpdate, net::server_send_snapshots.system());
+    app.add_system(crosshair::add_crosshairs.system());
+    app.add_system(crosshair::update_crosshair.system());
+    app.add_system(hud::update_player_panels.system());
+    app.add_system(hud::update_health_hud.system());
+    app.add_system(hud::update_stamina_hud.system());
+    app.add_system(hud::update_weapon_hud.system());
+    app.add_system(hud::update_buff_hud.system());
+    app.add_system(hud::update_progress_hud.system());
+    app.add_system(boss::update_boss_hud.system());
 
     app.run();
 }
 
-fn update_text(mut text_query: Query<&mut Text>, player_query: Query<&Player>) {
-    if let (Ok(player), Ok(mut text)) = (player_query.single(), text_query.single_mut()) {
-        if let Some(gun) = &player.gun {
-            text.sections[0].value = match gun.reloading() {
-                true => "RELOADING!".to_string(),
-                false => format!("{} Rounds: {}", gun.name(), gun.left_in_mag()),
-            };
-        } else {
-            text.sections[0].value = "No gun".to_string();
-        }
-    }
-}
-
-fn face_mouse(mut player_query: Query<(&Player, &mut Rad<f32>, &mut Transform)>, windows: Res<Windows>) {
+fn face_mouse(
+    mut player_query: Query<(&GamepadInput, &mut Rad<f32>, &Transform), (With<Player>, Without<Downed>)>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    windows: Res<Windows>,
+) {
     let window = windows.get_primary().unwrap();
-    let cursor_loc_opt = window.cursor_position();
-    if let (Ok((_, mut angle, transform)), Some(cursor_location)) =
-        (player_query.single_mut(), cursor_loc_opt)
-    {
-        let cursor_location_corrected = Vec2::new(
-            cursor_location.x - (window.width() / 2.0),
-            cursor_location.y - (window.height() / 2.0),
-        );
+    let cursor_loc_opt = camera_query
+        .single()
+        .ok()
+        .and_then(|camera| crosshair::cursor_world_location(window, camera));
+
+    for (gamepad, mut angle, transform) in player_query.iter_mut() {
+        // Twin-stick aiming points the player straight along the right stick
+        if gamepad.device == InputDevice::Gamepad {
+            if let Some(aim) = gamepad.aim {
+                *angle = Rad(aim.y.atan2(aim.x));
+            }
+            continue;
+        }
 
-        let player_location = transform.translation.truncate();
+        if let Some(cursor_location_corrected) = cursor_loc_opt {
+            let player_location = transform.translation.truncate();
 
-        let angle_calc = player_location.get_angle_to(&cursor_location_corrected);
+            let angle_calc = player_location.get_angle_to(&cursor_location_corrected);
 
-        // transform.rotation = Quat::from_rotation_z(angle_calc.0);
-        *angle = angle_calc;
+            // transform.rotation = Quat::from_rotation_z(angle_calc.0);
+            *angle = angle_calc;
+        }
     }
 }
 
-fn move_player(input: Res<Input<KeyCode>>, mut player_query: Query<(&Player, &mut Transform)>) {
-    if let Ok((_player, mut trans)) = player_query.single_mut() {
+fn move_player(
+    time: Res<Time>,
+    mut player_query: Query<
+        (&Player, &PlayerActions, &GamepadInput, &Buffs, &mut Stamina, &mut Dash, &mut Transform),
+        Without<Downed>,
+    >,
+) {
+    for (player, actions, gamepad, buffs, mut stamina, mut dash, mut trans) in player_query.iter_mut() {
+        if let Some(direction) = dash.tick(time.delta()) {
+            trans.translation += (direction * DASH_SPEED).extend(0.0);
+            continue;
+        }
+
         let mut translation = Vec2::new(0.0, 0.0);
 
-        if input.pressed(KeyCode::W) {
+        if actions.pressed(Action::MoveUp) {
             translation.y += MOVE_SPEED;
         }
-        if input.pressed(KeyCode::S) {
+        if actions.pressed(Action::MoveDown) {
             translation.y -= MOVE_SPEED;
         }
-        if input.pressed(KeyCode::A) {
+        if actions.pressed(Action::MoveLeft) {
             translation.x -= MOVE_SPEED;
         }
-        if input.pressed(KeyCode::D) {
+        if actions.pressed(Action::MoveRight) {
             translation.x += MOVE_SPEED;
         }
 
         translation.clamp_max_length(MOVE_SPEED);
+        if gamepad.movement != Vec2::ZERO {
+            translation = gamepad.movement * MOVE_SPEED;
+        }
+
+        let moving = translation != Vec2::ZERO;
+        if moving && actions.just_pressed(Action::Dash) && stamina.spend(DASH_STAMINA) {
+            dash.start(translation / MOVE_SPEED);
+            continue;
+        }
+
+        if moving && actions.pressed(Action::Sprint) && stamina.drain(SPRINT_STAMINA_PER_SECOND * time.delta_seconds()) {
+            translation *= SPRINT_FACTOR;
+        } else {
+            stamina.regen(time.delta());
+        }
+        if player.gun.as_ref().map(|gun| gun.reloading()).unwrap_or(false) {
+            translation *= RELOAD_MOVE_FACTOR;
+        }
+        translation *= buffs.speed_factor();
 
         trans.translation += Vec3::new(translation.x, translation.y, 0.0);
     }
 }
 
-fn player_input(input: Res<Input<KeyCode>>, mut player_query: Query<&mut Player>) {
-    if let Ok(mut player) = player_query.single_mut() {
-        if input.pressed(KeyCode::R) {
+fn player_input(mut player_query: Query<(&mut Player, &PlayerActions), Without<Downed>>) {
+    for (mut player, actions) in player_query.iter_mut() {
+        if actions.just_pressed(Action::Reload) {
             if let Some(gun) = &mut player.gun {
                 gun.reload();
             }
         }
+        if actions.just_pressed(Action::SwitchWeapon) {
+            player.cycle_gun();
+        }
     }
 }
 
-fn move_zom(mut player_query: QuerySet<(Query<PeopleBorrow>, Query<(&Zom, &mut Transform, &mut Rad<f32>)>)>) {
-    let mut _player_transform = Transform::from_xyz(0.0, 0.0, 0.0);
-    if let Ok((_player, player_trans)) = player_query.q0().single() {
-        _player_transform = *player_trans;
-    } else {
-        return;
-    }
+fn move_zom(mut zom_query: Query<(&Zom, &Perception, &HitReaction, &mut Transform, &mut Rad<f32>)>) {
+    for (zom, perception, reaction, mut zom_trans, mut angle) in zom_query.iter_mut() {
+        if reaction.stunned() {
+            continue;
+        }
 
-    for (zom, mut zom_trans, mut angle) in player_query.q1_mut().iter_mut() {
-        let unit_vec = Velocity::between_transforms(
-            &zom_trans.translation.truncate(),
-            &_player_transform.translation.truncate(),
-        )
-        .unit_vec();
+        let target = match perception.target() {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let to_target = Velocity::between_transforms(&zom_trans.translation.truncate(), &target);
+        if to_target.magnitude() < 1.0 {
+            continue;
+        }
+        let unit_vec = to_target.unit_vec();
 
         let speed = match zom.zom_type {
             ZomType::Default => ZOM_SPEED,
             ZomType::Strong => STRONG_ZOM_SPEED,
-        };
+            ZomType::Boss => BOSS_SPEED,
+        } * perception.speed_factor();
 
         zom_trans.translation.x += unit_vec.0 * speed;
         zom_trans.translation.y += unit_vec.1 * speed;
-        *angle = zom_trans
-            .translation
-            .truncate()
-            .get_angle_to(&_player_transform.translation.truncate());
+        *angle = zom_trans.translation.truncate().get_angle_to(&target);
     }
 }
 
 fn zom_bullet_collision(
-    bullet_query: Query<(&Bullet, &Transform, Entity)>,
-    zom_query: Query<(&Zom, &Transform, Entity)>,
+    mut bullet_query: Query<(&mut Bullet, &Vel, &Transform, Entity)>,
+    mut zom_query: Query<(&Zom, &Transform, &mut Health, &mut HitReaction, Entity)>,
+    mut hit_writer: EventWriter<ZomHit>,
+    mut killed_writer: EventWriter<ZomKilled>,
     mut commands: Commands,
 ) {
-    for (zom, zom_trans, zom_entity) in zom_query.iter() {
-        for (_bullet, bullet_trans, bullet_entity) in bullet_query.iter() {
+    for (mut bullet, vel, bullet_trans, bullet_entity) in bullet_query.iter_mut() {
+        for (zom, zom_trans, mut health, mut reaction, zom_entity) in zom_query.iter_mut() {
+            // Already killed by another bullet this frame, or already passed through
+            if health.is_dead() || bullet.hit.contains(&zom_entity) {
+                continue;
+            }
+
             let dist = Velocity::between_transforms(
                 &zom_trans.translation.truncate(),
                 &bullet_trans.translation.truncate(),
             )
             .magnitude();
 
-            match zom.zom_type {
-                ZomType::Default => {
-                    if dist < ZOM_SIZE {
-                        commands.entity(zom_entity).despawn();
-                        commands.entity(bullet_entity).despawn();
-                    }
+            let (size, mass) = zom.zom_type.size_and_mass();
+
+            if dist < size {
+                let damage = bullet.damage();
+                health.damage(damage);
+
+                let (x, y) = vel.0.unit_vec();
+                reaction.hit(Vec2::new(x, y) * bullet.ballistics.knockback / mass);
+
+                hit_writer.send(ZomHit {
+                    zom_type: zom.zom_type,
+                    location: bullet_trans.translation.truncate(),
+                    direction: Vec2::new(x, y),
+                    damage,
+                });
+
+                if health.is_dead() {
+                    killed_writer.send(ZomKilled {
+                        zom: zom_entity,
+                        zom_type: zom.zom_type,
+                        location: zom_trans.translation.truncate(),
+                    });
                 }
-                ZomType::Strong => {
-                    if dist < STRONG_ZOM_SIZE {
-                        commands.entity(zom_entity).despawn();
-                        commands.entity(bullet_entity).despawn();
-                    }
+
+                if !bullet.pierce(zom_entity) {
+                    commands.entity(bullet_entity).despawn();
+                    break;
                 }
-            };
+            }
         }
     }
 }
 
+/// Tracks how far bullets have flown and stops or bounces them at walls
+fn bullet_travel(
+    mut commands: Commands,
+    wall_query: Query<(&wall::Wall, &Transform)>,
+    mut bullet_query: Query<(&mut Bullet, &mut Vel, &mut Transform, Entity), Without<wall::Wall>>,
+) {
+    for (mut bullet, mut vel, mut trans, entity) in bullet_query.iter_mut() {
+        let to = trans.translation.truncate();
+        let from = to - vel.0;
+        bullet.travelled += vel.0.length();
+
+        let (fraction, normal) = match wall::first_hit(&wall_query, from, to) {
+            Some(hit) => hit,
+            None => continue,
+        };
+
+        if bullet.ricochets_left == 0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        bullet.ricochets_left -= 1;
+
+        // Reflect off the wall and spend the rest of this frame's movement going back out
+        let impact = from + (to - from) * fraction;
+        vel.0 -= 2.0 * vel.0.dot(normal) * normal;
+        trans.translation = (impact + vel.0 * (1.0 - fraction)).extend(trans.translation.z);
+        trans.rotation = Quat::from_rotation_z(vel.0.y.atan2(vel.0.x));
+    }
+}
+
 fn spawn_zom(
     mut commands: Commands, 
-    mut meshes: ResMut<Assets<Mesh>>,
-    materials: Res<Materials>,
-    windows: Res<Windows>
+    sprite_sheets: Res<SpriteSheets>,
+    windows: Res<Windows>,
+    mut wave: ResMut<wave::Wave>,
 ) {
+    if wave.remaining == 0 {
+        return;
+    }
+
     let mut random = rand::thread_rng();
     if random.gen_bool(0.01) {
-        let mut translation = Vec3::new(0.0, 0.0, 0.0);
-        let window = windows.get_primary().unwrap();
-        let window_size = (window.width(), window.height());
-
-        // Choose which edge to spawn on
-        match random.gen_range(1..=4) {
-            // Left side
-            1 => {
-                translation.x = -window_size.0 / 2.0;
-                translation.y = random.gen_range((-window_size.1 / 2.0)..(window_size.1 / 2.0));
-            }
-            // Top side
-            2 => {
-                translation.y = window_size.1 / 2.0;
-                translation.x = random.gen_range((-window_size.0 / 2.0)..(window_size.0 / 2.0));
-            }
-            // Right side
-            3 => {
-                translation.x = window_size.0 / 2.0;
-                translation.y = random.gen_range((-window_size.1 / 2.0)..(window_size.1 / 2.0));
-            }
-            // Bottom side
-            4 => {
-                translation.y = -window_size.1 / 2.0;
-                translation.x = random.gen_range((-window_size.0 / 2.0)..(window_size.0 / 2.0));
-            }
-            _ => {
-                panic!("What the fek? how did this happen?");
-            }
+        let translation = edge_spawn_location(&mut random, windows.get_primary().unwrap());
+
+        let zom_type = match random.gen_range(0..10) {
+            0 | 1 | 2 | 3 | 4 | 5 | 6 => ZomType::Default,
+            _ => ZomType::Strong,
+        };
+
+        spawn_zom_at(&mut commands, &sprite_sheets, zom_type, translation);
+        wave.remaining -= 1;
+    }
+}
+
+fn edge_spawn_location(random: &mut impl Rng, window: &Window) -> Vec3 {
+    let mut translation = Vec3::new(0.0, 0.0, 0.0);
+    let window_size = (window.width(), window.height());
+
+    // Choose which edge to spawn on
+    match random.gen_range(1..=4) {
+        // Left side
+        1 => {
+            translation.x = -window_size.0 / 2.0;
+            translation.y = random.gen_range((-window_size.1 / 2.0)..(window_size.1 / 2.0));
+        }
+        // Top side
+        2 => {
+            translation.y = window_size.1 / 2.0;
+            translation.x = random.gen_range((-window_size.0 / 2.0)..(window_size.0 / 2.0));
+        }
+        // Right side
+        3 => {
+            translation.x = window_size.0 / 2.0;
+            translation.y = random.gen_range((-window_size.1 / 2.0)..(window_size.1 / 2.0));
+        }
+        // Bottom side
+        4 => {
+            translation.y = -window_size.1 / 2.0;
+            translation.x = random.gen_range((-window_size.0 / 2.0)..(window_size.0 / 2.0));
+        }
+        _ => {
+            panic!("What the fek? how did this happen?");
         }
+    }
 
-        let mut mesh = Mesh::from(shape::Quad::new(Vec2::new(1.0, 1.0)));
-
-        let x_diff = 1./12.;
-        let y_diff = 1./8.;
-        let uv_vec = vec![
-            [0.0 + (x_diff * 1.), 0.5 + y_diff],
-            [0.0 + (x_diff * 1.), 0.5],
-            [x_diff + (x_diff * 1.), 0.5],
-            [x_diff + (x_diff * 1.), 0.5 + y_diff],
-        ];
-
-        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uv_vec);
-
-        match random.gen_range(0..10) {
-            0 | 1 | 2 | 3 | 4 | 5 | 6 => {
-                commands
-                    .spawn_bundle(SpriteBundle {
-                        sprite: Sprite::new(Vec2::new(30.0, 50.0)),
-                        material: materials.zom_sprite.clone(),
-                        mesh: meshes.add(mesh),
-                        transform: Transform::from_xyz(translation.x, translation.y, translation.z),
-                        ..Default::default()
-                    })
-                    .insert(SpriteAnimationCapture {
-                        x_diff: 1./ 12.,
-                        y_diff: 1./ 8.,
-                        start_point: [1, 4],
-                    })
-                    .insert(Rad(0.0f32))
-                    .insert(Zom::default());
-            }
-            7 | 8 | 9 => {
-                commands
-                    .spawn_bundle(SpriteBundle {
-                        sprite: Sprite::new(Vec2::new(STRONG_ZOM_SIZE, STRONG_ZOM_SIZE)),
-                        material: materials.strong_zom.clone(),
-                        transform: Transform::from_xyz(translation.x, translation.y, translation.z),
-                        ..Default::default()
-                    })
-                    .insert(Rad(0.0f32))
-                    .insert(Zom {
-                        zom_type: ZomType::Strong,
-                    });
-            }
-            _ => {}
+    translation
+}
+
+fn spawn_zom_at(
+    commands: &mut Commands,
+    sprite_sheets: &SpriteSheets,
+    zom_type: ZomType,
+    translation: Vec3,
+) -> Entity {
+    match zom_type {
+        ZomType::Default => {
+            commands
+                .spawn_bundle(sprite_sheets.bundle("zom", translation))
+                .insert(CharacterAnimation::new("zom"))
+                .insert(Rad(0.0f32))
+                .insert(Health::new(ZOM_HEALTH))
+                .insert(Perception::for_zom_type(&ZomType::Default))
+                .insert(HitReaction::default())
+                .insert(Zom::default())
+                .id()
+        }
+        ZomType::Strong => {
+            commands
+                .spawn_bundle(sprite_sheets.bundle("strong_zom", translation))
+                .insert(CharacterAnimation::new("strong_zom"))
+                .insert(Rad(0.0f32))
+                .insert(Health::new(ZOM_HEALTH))
+                .insert(Perception::for_zom_type(&ZomType::Strong))
+                .insert(HitReaction::default())
+                .insert(Zom {
+                    zom_type: ZomType::Strong,
+                    ..Default::default()
+                })
+                .id()
+        }
+        ZomType::Boss => {
+            commands
+                .spawn_bundle(sprite_sheets.bundle("boss", translation))
+                .insert(CharacterAnimation::new("boss"))
+                .insert(Rad(0.0f32))
+                .insert(Health::new(BOSS_HEALTH))
+                .insert(Perception::for_zom_type(&ZomType::Boss))
+                .insert(HitReaction::default())
+                .insert(Zom {
+                    zom_type: ZomType::Boss,
+                    ..Default::default()
+                })
+                .id()
         }
     }
 }
 
 fn shoot_bullet(
-    commands: Commands,
-    mouse: Res<Input<MouseButton>>,
+    mut commands: Commands,
     materials: Res<Materials>,
-    mut player_query: Query<(&mut Player, &Rad<f32>, &Transform)>,
+    mut player_query: Query<(&mut Player, &PlayerActions, &Buffs, &Rad<f32>, &Transform), Without<Downed>>,
     time: Res<Time>,
+    mut shot_writer: EventWriter<ShotFired>,
 ) {
-    if let Ok((mut player, angle, trans)) = player_query.single_mut() {
+    for (mut player, actions, buffs, angle, trans) in player_query.iter_mut() {
         if let Some(gun) = player.gun.as_mut() {
-            gun.shoot(time, mouse, trans, *angle, materials, commands);
+            let muzzle = gun_sprite::muzzle_location(trans.translation.truncate(), *angle, &**gun);
+            let aim = gun::Aim {
+                muzzle,
+                angle: *angle,
+                damage_factor: buffs.damage_factor(),
+            };
+            let trigger = match gun.automatic() {
+                true => actions.pressed(Action::Fire),
+                false => actions.just_pressed(Action::Fire),
+            };
+            if gun.shoot(&time, trigger, aim, &materials, &mut commands) {
+                shot_writer.send(ShotFired {
+                    origin: muzzle,
+                    angle: *angle,
+                    loudness: gun.loudness(),
+                    camera_kick: gun.recoil().camera_kick(),
+                });
+            }
         }
     }
 }
 
+fn settle_recoil(time: Res<Time>, mut player_query: Query<(&mut Player, &CharacterAnimation)>) {
+    for (mut player, animation) in player_query.iter_mut() {
+        if let Some(gun) = player.gun.as_mut() {
+            gun.recoil_mut().settle(time.delta_seconds(), animation.is_moving());
+        }
+    }
+}
+
+/// Pushes the camera away from each shot and eases it back
+fn kick_camera(
+    settings: Res<settings::Settings>,
+    mut offset: Local<Vec2>,
+    mut shot_reader: EventReader<ShotFired>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    for shot in shot_reader.iter() {
+        if settings.camera_kick {
+            *offset -= Vec2::new(shot.angle.cos(), shot.angle.sin()) * shot.camera_kick;
+        }
+    }
+    *offset *= CAMERA_KICK_DECAY;
+
+    if let Ok(mut trans) = camera_query.single_mut() {
+        trans.translation.x = offset.x;
+        trans.translation.y = offset.y;
+    }
+}
+
 fn despawn_bullet(
     mut commands: Commands,
     bullet_query: Query<(&Bullet, &Transform, Entity)>,
@@ -384,152 +860,125 @@ fn move_elements(mut vel_query: Query<(&Vel, &mut Transform)>) {
     }
 }
 
-struct SpriteAnimationCapture {
-    x_diff: f32,
-    y_diff: f32,
-    start_point: [u32; 2],
+fn apply_knockback(time: Res<Time>, mut zom_query: Query<(&mut HitReaction, &mut Transform)>) {
+    for (mut reaction, mut trans) in zom_query.iter_mut() {
+        reaction.stun.tick(time.delta());
+
+        trans.translation += reaction.velocity.extend(0.0);
+        reaction.velocity *= KNOCKBACK_DECAY;
+    }
 }
 
-fn change_sprite(
-    query: Query<(&Rad<f32>, &SpriteAnimationCapture, &Handle<Mesh>)>,
-    mut mesh_access: ResMut<Assets<Mesh>>,
+/// Normal and strong zombies bite players they touch, the boss has its own attacks
+fn bite_players(
+    time: Res<Time>,
+    mut zom_query: Query<(&mut Zom, &HitReaction, &Transform)>,
+    player_query: Query<(&Transform, Entity), (With<Player>, Without<Downed>)>,
+    mut hurt_writer: EventWriter<PlayerHurt>,
 ) {
-    for (radian, sprite_info, mesh) in query.iter() {
-        let angle = (radian.0 * (180.0 / std::f32::consts::PI)) as i32;
-        let sprite_mesh = match angle {
-            (-45..=45) => [
-                (sprite_info.start_point[0]) as f32,
-                (2 + sprite_info.start_point[1]) as f32,
-            ],
-            (46..=135) => [
-                (sprite_info.start_point[0]) as f32,
-                (3 + sprite_info.start_point[1]) as f32,
-            ],
-            (136..=225) => [
-                (sprite_info.start_point[0]) as f32,
-                (1 + sprite_info.start_point[1]) as f32,
-            ],
-            (226..=270) => [
-                (sprite_info.start_point[0]) as f32,
-                (sprite_info.start_point[1]) as f32,
-            ],
-            (-90..=-44) => [
-                (sprite_info.start_point[0]) as f32,
-                (sprite_info.start_point[1]) as f32,
-            ],
-            _ => {
-                println!("Angle at: {}", radian.0);
-                [
-                    (sprite_info.start_point[0]) as f32,
-                    (2 + sprite_info.start_point[1]) as f32,
-                ]
-            }
+    for (mut zom, reaction, zom_trans) in zom_query.iter_mut() {
+        let (damage, size) = match zom.zom_type {
+            ZomType::Default => (ZOM_BITE_DAMAGE, ZOM_SIZE),
+            ZomType::Strong => (STRONG_ZOM_BITE_DAMAGE, STRONG_ZOM_SIZE),
+            ZomType::Boss => continue,
         };
+        if !zom.bite_cooldown.tick(time.delta()).finished() || reaction.stunned() {
+            continue;
+        }
 
-        let x_diff = &sprite_info.x_diff;
-        let y_diff = &sprite_info.y_diff;
-
-        let uv_vec = vec![
-            [x_diff * sprite_mesh[0], y_diff * sprite_mesh[1] + y_diff],
-            [x_diff * sprite_mesh[0], y_diff * sprite_mesh[1]],
-            [x_diff * sprite_mesh[0] + x_diff, y_diff * sprite_mesh[1]],
-            [
-                x_diff * sprite_mesh[0] + x_diff,
-                y_diff * sprite_mesh[1] + y_diff,
-            ],
-        ];
+        let zom_location = zom_trans.translation.truncate();
+        let bitten = player_query
+            .iter()
+            .find(|(player_trans, _)| (player_trans.translation.truncate() - zom_location).length() < size + BITE_REACH);
+
+        if let Some((_, player)) = bitten {
+            hurt_writer.send(PlayerHurt {
+                player,
+                amount: damage,
+                source: zom_location,
+            });
+            zom.bite_cooldown = Timer::from_seconds(BITE_SECONDS, false);
+        }
+    }
+}
 
-        let mesh = mesh_access
-            .get_mut(mesh)
-            .expect("Failed to get mesh handle!");
+fn hurt_player(
+    mut hurt_reader: EventReader<PlayerHurt>,
+    mut player_query: Query<
+        (&mut Health, &mut Armour, &mut Transform, &Dash, &Buffs),
+        (With<Player>, Without<Downed>),
+    >,
+) {
+    for hurt in hurt_reader.iter() {
+        if let Ok((mut health, mut armour, mut trans, dash, buffs)) = player_query.get_mut(hurt.player) {
+            if dash.invulnerable() || buffs.active(Buff::Invulnerable) {
+                continue;
+            }
+            let amount = armour.absorb(hurt.amount);
+            health.damage(amount);
 
-        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uv_vec);
+            // Shove the player away from whatever hurt them
+            let away = trans.translation.truncate() - hurt.source;
+            if away.length() > f32::EPSILON {
+                trans.translation += (away / away.length() * HURT_PUSH).extend(0.0);
+            }
+        }
     }
 }
 
 // SETUP FUNCTIONS
 // ----------------------------------
 fn load_camera(mut commands: Commands) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
     commands.spawn_bundle(UiCameraBundle::default());
 }
 
-fn load_text(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn_bundle(TextBundle {
-        style: Style {
-            position_type: PositionType::Absolute,
-            position: Rect {
-                top: Val::Px(5.0),
-                left: Val::Px(5.0),
-                ..Default::default()
-            },
-            ..Default::default()
-        },
-        text: Text {
-            sections: vec![TextSection {
-                value: "Rounds".to_string(),
-                style: TextStyle {
-                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                    font_size: 20.0,
-                    color: Color::WHITE,
-                },
-            }],
-            ..Default::default()
-        },
-        ..Default::default()
-    });
-}
-
 fn load_materials(
     mut commands: Commands, 
     mut materials: ResMut<Assets<ColorMaterial>>,
-    asset_server: Res<AssetServer>,
 ) {
-    let zom_texture_handle = asset_server.load("images/people/zoms.png");
     commands.insert_resource(Materials {
         bullet: materials.add(Color::GRAY.into()),
         zom: materials.add(Color::RED.into()),
-        strong_zom: materials.add(Color::CYAN.into()),
-        zom_sprite: materials.add(zom_texture_handle.into()),
+        blood: materials.add(Color::rgba(0.45, 0.0, 0.0, 0.8).into()),
+        slam_marker: materials.add(Color::rgba(1.0, 0.2, 0.0, 0.3).into()),
+        reward: materials.add(Color::GOLD.into()),
+        tracer: materials.add(Color::rgba(1.0, 0.95, 0.7, 0.8).into()),
+        grenade: materials.add(Color::DARK_GREEN.into()),
+        pickup: pickup::PickupKind::ALL
+            .iter()
+            .map(|kind| (*kind, materials.add(kind.color().into())))
+            .collect(),
     });
 }
 
-fn load_player(
-    mut commands: Commands,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    asset_server: Res<AssetServer>,
-) {
-    let texture_handle = asset_server.load("images/people/players.png");
-
-    let mut mesh = Mesh::from(shape::Quad::new(Vec2::new(1.0, 1.0)));
-    let x_diff = 1. / 12.;
-    let y_diff = 1. / 8.;
-    let uv_vec = vec![
-        [0.0 + (x_diff * 1.), 0.5 + y_diff],
-        [0.0 + (x_diff * 1.), 0.5],
-        [x_diff + (x_diff * 1.), 0.5],
-        [x_diff + (x_diff * 1.), 0.5 + y_diff],
-    ];
-    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uv_vec);
-
+/// Adds a player at `location`, controlled through `input`
+fn spawn_player(commands: &mut Commands, sprite_sheets: &SpriteSheets, number: usize, input: GamepadInput, location: Vec2) -> Entity {
     commands
-        .spawn_bundle(SpriteBundle {
-            sprite: Sprite::new(Vec2::new(30.0, 50.0)),
-            material: materials.add(texture_handle.into()),
-            // material: materials.add(Color::ORANGE_RED.into()),
-            mesh: meshes.add(mesh),
-            transform: Transform::from_xyz(0.0, 0.0, 0.1),
-            ..Default::default()
-        })
-        .insert(SpriteAnimationCapture {
-            x_diff,
-            y_diff,
-            start_point: [1, 4],
-        })
+        .spawn_bundle(sprite_sheets.bundle("player", location.extend(0.1)))
+        .insert(CharacterAnimation::new("player"))
         .insert(Rad(0.0f32))
+        .insert(Health::new(PLAYER_HEALTH))
+        .insert(melee::Melee::default())
+        .insert(Stamina::new(stamina::PLAYER_STAMINA))
+        .insert(Dash::default())
+        .insert(Buffs::default())
+        .insert(Armour::default())
+        .insert(input)
+        .insert(PlayerActions::default())
         .insert(Player {
-            gun: Some(gun::Shotgun::new()),
-        });
+            number,
+            gun: Some(gun::Firearm::new(&gun::SHOTGUN)),
+            spare_guns: vec![gun::Firearm::new(&gun::PISTOL), gun::Firearm::new(&gun::RIFLE)],
+            grenades: grenade::START_GRENADES,
+        })
+        .with_children(gun_sprite::spawn_held_gun)
+        .id()
+}
+
+fn load_player(mut commands: Commands, sprite_sheets: Res<SpriteSheets>) {
+    spawn_player(&mut commands, &sprite_sheets, 0, GamepadInput::keyboard_mouse(), Vec2::ZERO);
 }
 // -----------------------------------
@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use cgmath::Rad;
+
+use crate::bindings::{Action, PlayerActions};
+use crate::coop::Downed;
+use crate::events::{ZomHit, ZomKilled};
+use crate::pickup::Buffs;
+use crate::{Health, HitReaction, Player, Zom};
+
+use std::f32::consts::PI;
+
+static MELEE_COOLDOWN_SECONDS: f32 = 0.6;
+/// Reach from the player's centre to the zombie's edge, in pixels
+static MELEE_RANGE: f32 = 35.0;
+/// Total width of the swing, in radians
+static MELEE_ARC: f32 = 2.0;
+static MELEE_DAMAGE: f32 = 5.0;
+static MELEE_KNOCKBACK: f32 = 8.0;
+
+/// A player's swing, usable whether or not their gun is ready
+pub struct Melee {
+    cooldown: Timer,
+}
+
+impl Default for Melee {
+    fn default() -> Self {
+        let mut cooldown = Timer::from_seconds(MELEE_COOLDOWN_SECONDS, false);
+        cooldown.tick(cooldown.duration());
+
+        Melee { cooldown }
+    }
+}
+
+pub fn swing_melee(
+    time: Res<Time>,
+    mut player_query: Query<
+        (&mut Melee, &PlayerActions, &Buffs, &Rad<f32>, &Transform),
+        (With<Player>, Without<Downed>),
+    >,
+    mut zom_query: Query<(&Zom, &Transform, &mut Health, &mut HitReaction, Entity), Without<Player>>,
+    mut hit_writer: EventWriter<ZomHit>,
+    mut killed_writer: EventWriter<ZomKilled>,
+) {
+    for (mut melee, actions, buffs, angle, player_trans) in player_query.iter_mut() {
+        melee.cooldown.tick(time.delta());
+        if !melee.cooldown.finished() || !actions.just_pressed(Action::Melee) {
+            continue;
+        }
+        melee.cooldown.reset();
+
+        let player_location = player_trans.translation.truncate();
+        let damage = MELEE_DAMAGE * buffs.damage_factor();
+        for (zom, zom_trans, mut health, mut reaction, zom_entity) in zom_query.iter_mut() {
+            let location = zom_trans.translation.truncate();
+            let offset = location - player_location;
+            let (size, mass) = zom.zom_type.size_and_mass();
+            if health.is_dead() || offset.length() > MELEE_RANGE + size {
+                continue;
+            }
+
+            // Wrap the difference to -PI..PI before comparing against the arc
+            let off_aim = (offset.y.atan2(offset.x) - angle.0 + PI).rem_euclid(2.0 * PI) - PI;
+            if off_aim.abs() > MELEE_ARC / 2.0 {
+                continue;
+            }
+
+            let direction = offset / offset.length().max(f32::EPSILON);
+            health.damage(damage);
+            reaction.hit(direction * MELEE_KNOCKBACK / mass);
+
+            hit_writer.send(ZomHit {
+                zom_type: zom.zom_type,
+                location,
+                direction,
+                damage,
+            });
+
+            if health.is_dead() {
+                killed_writer.send(ZomKilled {
+                    zom: zom_entity,
+                    zom_type: zom.zom_type,
+                    location,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,1044 @@
+use bevy::prelude::*;
+use bincode::Options;
+use cgmath::Rad;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::animation::{CharacterAnimation, SpriteSheets};
+use crate::bindings::{Action, PlayerActions};
+use crate::gamepad::GamepadInput;
+use crate::{Bullet, Health, Player, Vel, Zom, ZomType};
+
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::{PI, TAU};
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+static DEFAULT_PORT: u16 = 7777;
+/// Largest datagram we'll read
+const MAX_PACKET: usize = 1500;
+/// Snapshots are split into datagrams no bigger than this, to stay under the usual MTU
+static MAX_SNAPSHOT_BYTES: usize = 1200;
+/// Most a snapshot's fields other than its entities can take up once encoded
+static SNAPSHOT_HEADER_BYTES: usize = 16;
+/// Entities further than this from a client's player aren't sent to it
+static SNAPSHOT_RADIUS: f32 = 1000.0;
+/// The nearest this many entities are sent when there are more in range
+static MAX_SNAPSHOT_ENTITIES: usize = 256;
+static SNAPSHOTS_PER_SECOND: f32 = 20.0;
+/// Clients not heard from in this long are dropped
+static CLIENT_TIMEOUT: f64 = 5.0;
+static HELLO_RETRY_SECONDS: f32 = 1.0;
+/// How far behind the newest snapshot other entities are drawn, so there's usually one either side
+static INTERPOLATION_DELAY: f64 = 0.1;
+/// Predicted positions closer than this to the server's are left alone
+static PREDICTION_TOLERANCE: f32 = 0.5;
+/// Inputs kept waiting for the server to acknowledge them
+static MAX_PENDING_INPUTS: usize = 240;
+/// Inputs a client can get ahead of the server by before the oldest are dropped to catch up
+static MAX_QUEUED_INPUTS: usize = 30;
+
+/// Whether this copy of the game plays alone, hosts, or joins someone else's game
+#[derive(Clone, Copy, PartialEq)]
+pub enum NetRole {
+    Offline,
+    /// Runs the simulation and accepts clients on the port
+    Host(u16),
+    Join(SocketAddr),
+    /// Hosts and connects this many headless test clients over localhost
+    LocalTest(usize),
+}
+
+impl NetRole {
+    /// Reads `--host [port]`, `--join <address>` or `--local-test [clients]` from the command line
+    pub fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let value = |index: usize| args.get(index + 1).filter(|arg| !arg.starts_with("--"));
+
+        for (index, arg) in args.iter().enumerate() {
+            match arg.as_str() {
+                "--host" => {
+                    let port = value(index).and_then(|port| port.parse().ok()).unwrap_or(DEFAULT_PORT);
+                    return NetRole::Host(port);
+                }
+                "--join" => match value(index).and_then(|address| resolve(address)) {
+                    Some(address) => return NetRole::Join(address),
+                    None => warn!("--join needs a server address, playing offline"),
+                },
+                "--local-test" => {
+                    let clients = value(index).and_then(|clients| clients.parse().ok()).unwrap_or(2);
+                    return NetRole::LocalTest(clients);
+                }
+                _ => {}
+            }
+        }
+        NetRole::Offline
+    }
+
+    /// Clients only draw what the server sends them instead of simulating the game
+    pub fn is_client(&self) -> bool {
+        matches!(self, NetRole::Join(_))
+    }
+}
+
+/// Parses `host:port`, falling back to the default port when there isn't one
+fn resolve(address: &str) -> Option<SocketAddr> {
+    address
+        .to_socket_addrs()
+        .or_else(|_| (address, DEFAULT_PORT).to_socket_addrs())
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+}
+
+#[derive(Serialize, Deserialize)]
+struct InputFrame {
+    /// Counts up every frame, so the server can say which input a snapshot includes
+    sequence: u32,
+    /// Left stick, the keys are sent as actions
+    movement: (f32, f32),
+    angle: f32,
+    actions: Vec<Action>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum ClientMessage {
+    Hello,
+    Input(InputFrame),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+enum EntityKind {
+    Player,
+    Zom,
+    StrongZom,
+    Boss,
+    Bullet,
+}
+
+/// What's in a player's held gun
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct AmmoState {
+    loaded: u16,
+    reserve: u16,
+    /// Seconds into the current reload step
+    reload_elapsed: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct EntityState {
+    /// The entity's bits on the server
+    id: u64,
+    kind: EntityKind,
+    x: f32,
+    y: f32,
+    angle: f32,
+    /// Fraction of health left
+    health: f32,
+    /// Only players holding a gun have ammo
+    ammo: Option<AmmoState>,
+}
+
+impl EntityState {
+    fn location(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    /// This state part way to `next`, turning the short way round
+    fn lerp(&self, next: &EntityState, t: f32) -> EntityState {
+        let turn = (next.angle - self.angle + PI).rem_euclid(TAU) - PI;
+        let location = self.location().lerp(next.location(), t);
+
+        EntityState {
+            x: location.x,
+            y: location.y,
+            angle: self.angle + turn * t,
+            ..*next
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum ServerMessage {
+    Welcome {
+        player: u64,
+    },
+    /// One datagram's worth of a snapshot, `part` of `parts` for the same tick
+    Snapshot {
+        tick: u32,
+        /// Latest input from this client the server had used
+        last_input: u32,
+        part: u8,
+        parts: u8,
+        entities: Vec<EntityState>,
+    },
+}
+
+/// Compact binary encoding every message is sent in
+fn encoding() -> impl Options {
+    bincode::DefaultOptions::new().with_limit(MAX_PACKET as u64)
+}
+
+/// Splits `entities` into runs that each fit in a snapshot datagram, always at least one
+fn split_snapshot(entities: &[EntityState]) -> Vec<&[EntityState]> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut bytes = SNAPSHOT_HEADER_BYTES;
+
+    for (index, state) in entities.iter().enumerate() {
+        let size = encoding().serialized_size(state).unwrap_or(0) as usize;
+        if bytes + size > MAX_SNAPSHOT_BYTES && index > start {
+            parts.push(&entities[start..index]);
+            start = index;
+            bytes = SNAPSHOT_HEADER_BYTES;
+        }
+        bytes += size;
+    }
+    parts.push(&entities[start..]);
+    parts
+}
+
+fn send<T: Serialize>(socket: &UdpSocket, address: SocketAddr, message: &T) {
+    let bytes = match encoding().serialize(message) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            warn!("Failed to serialise network message: {}", err);
+            return;
+        }
+    };
+    if let Err(err) = socket.send_to(&bytes, address) {
+        warn!("Failed to send to {}: {}", address, err);
+    }
+}
+
+/// Next message waiting on the socket, skipping any that don't parse
+fn receive<T: DeserializeOwned>(socket: &UdpSocket, buffer: &mut [u8]) -> Option<(T, SocketAddr)> {
+    loop {
+        let (length, address) = match socket.recv_from(buffer) {
+            Ok(received) => received,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => return None,
+            // Windows reports a closed port on the other end as an error on ours
+            Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+            Err(err) => {
+                warn!("Failed to receive: {}", err);
+                return None;
+            }
+        };
+
+        if let Ok(message) = encoding().deserialize(&buffer[..length]) {
+            return Some((message, address));
+        }
+    }
+}
+
+fn bind(address: SocketAddr) -> Option<UdpSocket> {
+    let socket = UdpSocket::bind(address).and_then(|socket| socket.set_nonblocking(true).map(|_| socket));
+    match socket {
+        Ok(socket) => Some(socket),
+        Err(err) => {
+            error!("Failed to open UDP socket on {}: {}", address, err);
+            None
+        }
+    }
+}
+
+/// Where the local player expected to be after each input the server hasn't confirmed yet
+#[derive(Default)]
+pub struct Prediction {
+    pending: VecDeque<(u32, Vec2)>,
+}
+
+impl Prediction {
+    pub fn record(&mut self, sequence: u32, location: Vec2) {
+        self.pending.push_back((sequence, location));
+        if self.pending.len() > MAX_PENDING_INPUTS {
+            self.pending.pop_front();
+        }
+    }
+
+    /// How far the prediction for `acknowledged` was off from where the server put the player,
+    /// which the player and every later prediction get moved by
+    pub fn reconcile(&mut self, acknowledged: u32, server_location: Vec2) -> Vec2 {
+        let mut predicted = None;
+        while let Some(&(sequence, location)) = self.pending.front() {
+            if sequence > acknowledged {
+                break;
+            }
+            if sequence == acknowledged {
+                predicted = Some(location);
+            }
+            self.pending.pop_front();
+        }
+
+        let error = match predicted {
+            Some(predicted) => server_location - predicted,
+            None => return Vec2::ZERO,
+        };
+        if error.length() < PREDICTION_TOLERANCE {
+            return Vec2::ZERO;
+        }
+
+        for (_, location) in self.pending.iter_mut() {
+            *location += error;
+        }
+        error
+    }
+}
+
+/// Parts of the newest snapshot that have arrived so far
+struct PartialSnapshot {
+    tick: u32,
+    /// Bit for each part received
+    received: u64,
+    entities: Vec<EntityState>,
+}
+
+/// Recent snapshots with when they arrived, for drawing other entities smoothly between them
+#[derive(Default)]
+struct SnapshotBuffer {
+    snapshots: VecDeque<(f64, u32, Vec<EntityState>)>,
+    partial: Option<PartialSnapshot>,
+}
+
+impl SnapshotBuffer {
+    /// Collects the parts of a snapshot, keeping it once they've all arrived.
+    /// A snapshot missing a part is dropped when a newer one starts
+    fn push_part(&mut self, received: f64, tick: u32, part: u8, parts: u8, entities: Vec<EntityState>) {
+        if part >= parts || parts > 64 {
+            return;
+        }
+        match &mut self.partial {
+            Some(partial) if partial.tick == tick => {
+                if partial.received & (1 << part) != 0 {
+                    return;
+                }
+                partial.received |= 1 << part;
+                partial.entities.extend(entities);
+            }
+            Some(partial) if partial.tick > tick => return,
+            _ => {
+                self.partial = Some(PartialSnapshot {
+                    tick,
+                    received: 1 << part,
+                    entities,
+                });
+            }
+        }
+
+        let complete = self
+            .partial
+            .as_ref()
+            .map(|partial| partial.received.count_ones() == parts as u32)
+            .unwrap_or(false);
+        if complete {
+            if let Some(partial) = self.partial.take() {
+                self.push(received, partial.tick, partial.entities);
+            }
+        }
+    }
+
+    fn push(&mut self, received: f64, tick: u32, entities: Vec<EntityState>) {
+        // Anything arriving out of order is already out of date
+        if self.snapshots.back().map(|(_, last, _)| tick <= *last).unwrap_or(false) {
+            return;
+        }
+        self.snapshots.push_back((received, tick, entities));
+    }
+
+    /// Every entity as it was at `time`, between the two snapshots either side of it
+    fn sample(&mut self, time: f64) -> Vec<EntityState> {
+        while self.snapshots.len() > 2 && self.snapshots[1].0 <= time {
+            self.snapshots.pop_front();
+        }
+
+        let (before, after) = match (self.snapshots.get(0), self.snapshots.get(1)) {
+            (Some(before), Some(after)) if before.0 <= time => (before, after),
+            (Some(only), _) => return only.2.clone(),
+            (None, _) => return Vec::new(),
+        };
+
+        let t = ((time - before.0) / (after.0 - before.0)).min(1.0) as f32;
+        let previous: HashMap<u64, &EntityState> = before.2.iter().map(|state| (state.id, state)).collect();
+        after
+            .2
+            .iter()
+            .map(|state| match previous.get(&state.id) {
+                Some(previous) => previous.lerp(state, t),
+                None => *state,
+            })
+            .collect()
+    }
+}
+
+/// Inputs from a client's player, standing in for keys and buttons on the server
+#[derive(Default)]
+pub struct RemoteInput {
+    actions: Vec<Action>,
+}
+
+impl RemoteInput {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.actions.contains(&action)
+    }
+}
+
+struct RemoteClient {
+    player: Entity,
+    /// Inputs received but not simulated yet, oldest first
+    inputs: VecDeque<InputFrame>,
+    /// Newest input received, anything older arriving late is dropped
+    last_received: u32,
+    /// Newest input the server has simulated, which snapshots acknowledge
+    last_input: u32,
+    last_heard: f64,
+}
+
+/// The host's socket and everyone connected to it
+pub struct NetServer {
+    socket: UdpSocket,
+    clients: HashMap<SocketAddr, RemoteClient>,
+    tick: u32,
+    send_timer: Timer,
+}
+
+impl NetServer {
+    fn new(socket: UdpSocket) -> Self {
+        NetServer {
+            socket,
+            clients: HashMap::new(),
+            tick: 0,
+            send_timer: Timer::from_seconds(1.0 / SNAPSHOTS_PER_SECOND, true),
+        }
+    }
+}
+
+/// A client's connection to the host, and what it knows of the host's game
+pub struct NetClient {
+    socket: UdpSocket,
+    server: SocketAddr,
+    /// Our player on the server, once it has welcomed us
+    player: Option<u64>,
+    sequence: u32,
+    hello_timer: Timer,
+    snapshots: SnapshotBuffer,
+    prediction: Prediction,
+}
+
+/// Something on the server this client draws a copy of
+pub struct NetProxy(u64);
+
+// SERVER
+// ----------------------------------
+/// Takes in hellos and inputs from clients, applies one queued input per client each tick,
+/// and drops clients that have gone quiet
+pub fn server_receive(
+    mut commands: Commands,
+    time: Res<Time>,
+    sprite_sheets: Res<SpriteSheets>,
+    server: Option<ResMut<NetServer>>,
+    player_query: Query<&Player>,
+    mut remote_query: Query<(&mut RemoteInput, &mut GamepadInput)>,
+) {
+    let mut server = match server {
+        Some(server) => server,
+        None => return,
+    };
+    let server = &mut *server;
+    let now = time.seconds_since_startup();
+    let mut taken: Vec<usize> = player_query.iter().map(|player| player.number).collect();
+    let mut buffer = [0u8; MAX_PACKET];
+
+    while let Some((message, address)) = receive::<ClientMessage>(&server.socket, &mut buffer) {
+        match message {
+            ClientMessage::Hello => {
+                // The welcome may have been lost, send it again
+                if let Some(client) = server.clients.get(&address) {
+                    send(&server.socket, address, &ServerMessage::Welcome { player: client.player.to_bits() });
+                    continue;
+                }
+                if taken.len() >= crate::coop::MAX_PLAYERS {
+                    continue;
+                }
+
+                let number = crate::coop::free_player_number(&taken);
+                let location = Vec2::new(crate::coop::JOIN_OFFSET * number as f32, 0.0);
+                let player = crate::spawn_player(&mut commands, &sprite_sheets, number, GamepadInput::remote(), location);
+                commands.entity(player).insert(RemoteInput::default());
+                taken.push(number);
+
+                info!("{} joined", address);
+                send(&server.socket, address, &ServerMessage::Welcome { player: player.to_bits() });
+                server.clients.insert(
+                    address,
+                    RemoteClient {
+                        player,
+                        inputs: VecDeque::new(),
+                        last_received: 0,
+                        last_input: 0,
+                        last_heard: now,
+                    },
+                );
+            }
+            ClientMessage::Input(frame) => {
+                let client = match server.clients.get_mut(&address) {
+                    Some(client) => client,
+                    None => continue,
+                };
+                client.last_heard = now;
+                if frame.sequence <= client.last_received {
+                    continue;
+                }
+
+                client.last_received = frame.sequence;
+                client.inputs.push_back(frame);
+                if client.inputs.len() > MAX_QUEUED_INPUTS {
+                    client.inputs.pop_front();
+                }
+            }
+        }
+    }
+
+    // The last input carries on until the next one arrives
+    for client in server.clients.values_mut() {
+        let frame = match client.inputs.pop_front() {
+            Some(frame) => frame,
+            None => continue,
+        };
+        if let Ok((mut remote, mut gamepad)) = remote_query.get_mut(client.player) {
+            client.last_input = frame.sequence;
+            remote.actions = frame.actions;
+            gamepad.movement = Vec2::new(frame.movement.0, frame.movement.1);
+            gamepad.aim = Some(Vec2::new(frame.angle.cos(), frame.angle.sin()));
+        }
+    }
+
+    let quiet: Vec<SocketAddr> = server
+        .clients
+        .iter()
+        .filter(|(_, client)| now - client.last_heard > CLIENT_TIMEOUT)
+        .map(|(address, _)| *address)
+        .collect();
+    for address in quiet {
+        if let Some(client) = server.clients.remove(&address) {
+            info!("{} timed out", address);
+            commands.entity(client.player).despawn_recursive();
+        }
+    }
+}
+
+/// Sends every client where the players, zombies and bullets near its player are
+pub fn server_send_snapshots(
+    time: Res<Time>,
+    server: Option<ResMut<NetServer>>,
+    player_query: Query<(&Player, &Rad<f32>, &Health, &Transform, Entity)>,
+    zom_query: Query<(&Zom, &Rad<f32>, &Health, &Transform, Entity)>,
+    bullet_query: Query<(&Vel, &Transform, Entity), With<Bullet>>,
+) {
+    let mut server = match server {
+        Some(server) => server,
+        None => return,
+    };
+    if !server.send_timer.tick(time.delta()).just_finished() || server.clients.is_empty() {
+        return;
+    }
+    server.tick += 1;
+
+    let state = |id: Entity, kind, trans: &Transform, angle: f32, health: f32| {
+        let location = trans.translation.truncate();
+        EntityState {
+            id: id.to_bits(),
+            kind,
+            x: location.x,
+            y: location.y,
+            angle,
+            health,
+            ammo: None,
+        }
+    };
+
+    let mut entities = Vec::new();
+    for (player, angle, health, trans, entity) in player_query.iter() {
+        let mut player_state = state(entity, EntityKind::Player, trans, angle.0, health.fraction());
+        player_state.ammo = player.gun.as_ref().map(|gun| {
+            let (loaded, reserve, reload_elapsed) = gun.magazine().state();
+            AmmoState {
+                loaded,
+                reserve,
+                reload_elapsed,
+            }
+        });
+        entities.push(player_state);
+    }
+    for (zom, angle, health, trans, entity) in zom_query.iter() {
+        let kind = match zom.zom_type {
+            ZomType::Default => EntityKind::Zom,
+            ZomType::Strong => EntityKind::StrongZom,
+            ZomType::Boss => EntityKind::Boss,
+        };
+        entities.push(state(entity, kind, trans, angle.0, health.fraction()));
+    }
+    for (vel, trans, entity) in bullet_query.iter() {
+        entities.push(state(entity, EntityKind::Bullet, trans, vel.0.y.atan2(vel.0.x), 1.0));
+    }
+
+    for (address, client) in server.clients.iter() {
+        let center = player_query
+            .get(client.player)
+            .map(|(_, _, _, trans, _)| trans.translation.truncate())
+            .unwrap_or(Vec2::ZERO);
+
+        let mut nearby: Vec<(f32, EntityState)> = entities
+            .iter()
+            .map(|state| ((state.location() - center).length(), *state))
+            .filter(|(dist, _)| *dist <= SNAPSHOT_RADIUS)
+            .collect();
+        nearby.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        nearby.truncate(MAX_SNAPSHOT_ENTITIES);
+        let nearby: Vec<EntityState> = nearby.into_iter().map(|(_, state)| state).collect();
+
+        let parts = split_snapshot(&nearby);
+        for (part, states) in parts.iter().enumerate() {
+            let snapshot = ServerMessage::Snapshot {
+                tick: server.tick,
+                last_input: client.last_input,
+                part: part as u8,
+                parts: parts.len() as u8,
+                entities: states.to_vec(),
+            };
+            send(&server.socket, *address, &snapshot);
+        }
+    }
+}
+// -----------------------------------
+
+// CLIENT
+// ----------------------------------
+/// Reads snapshots, correcting our own player and its ammo against the server and queueing the rest to draw
+pub fn client_receive(
+    time: Res<Time>,
+    client: Option<ResMut<NetClient>>,
+    mut player_query: Query<(&mut Player, &mut Health, &mut Transform)>,
+) {
+    let mut client = match client {
+        Some(client) => client,
+        None => return,
+    };
+    let client = &mut *client;
+
+    if client.player.is_none() && client.hello_timer.tick(time.delta()).just_finished() {
+        send(&client.socket, client.server, &ClientMessage::Hello);
+    }
+
+    let mut buffer = [0u8; MAX_PACKET];
+    while let Some((message, address)) = receive::<ServerMessage>(&client.socket, &mut buffer) {
+        if address != client.server {
+            continue;
+        }
+
+        match message {
+            ServerMessage::Welcome { player } => {
+                if client.player.is_none() {
+                    info!("Joined {}", client.server);
+                }
+                client.player = Some(player);
+            }
+            ServerMessage::Snapshot {
+                tick,
+                last_input,
+                part,
+                parts,
+                entities,
+            } => {
+                let ours = entities.iter().find(|state| Some(state.id) == client.player);
+                if let (Some(ours), Ok((mut player, mut health, mut trans))) = (ours, player_query.single_mut()) {
+                    let correction = client.prediction.reconcile(last_input, ours.location());
+                    trans.translation += correction.extend(0.0);
+                    health.current = health.max * ours.health;
+
+                    if let (Some(ammo), Some(gun)) = (ours.ammo, player.gun.as_mut()) {
+                        gun.magazine_mut().set_state(ammo.loaded, ammo.reserve, ammo.reload_elapsed);
+                    }
+                }
+
+                client
+                    .snapshots
+                    .push_part(time.seconds_since_startup(), tick, part, parts, entities);
+            }
+        }
+    }
+}
+
+/// Sends this frame's input to the server, remembering where we predicted it would put us
+pub fn client_send_input(
+    client: Option<ResMut<NetClient>>,
+    player_query: Query<(&PlayerActions, &GamepadInput, &Rad<f32>, &Transform), With<Player>>,
+) {
+    let mut client = match client {
+        Some(client) => client,
+        None => return,
+    };
+    let (actions, gamepad, angle, trans) = match (client.player, player_query.single()) {
+        (Some(_), Ok(player)) => player,
+        _ => return,
+    };
+
+    client.sequence += 1;
+    let sequence = client.sequence;
+    client.prediction.record(sequence, trans.translation.truncate());
+
+    let frame = InputFrame {
+        sequence,
+        movement: (gamepad.movement.x, gamepad.movement.y),
+        angle: angle.0,
+        actions: Action::ALL.iter().copied().filter(|action| actions.pressed(*action)).collect(),
+    };
+    send(&client.socket, client.server, &ClientMessage::Input(frame));
+}
+
+/// Moves copies of everything on the server to where it was a moment ago,
+/// adding copies of new entities and removing ones the server no longer has
+pub fn interpolate_proxies(
+    mut commands: Commands,
+    time: Res<Time>,
+    sprite_sheets: Res<SpriteSheets>,
+    materials: Res<crate::Materials>,
+    client: Option<ResMut<NetClient>>,
+    mut proxy_query: Query<(&NetProxy, &mut Transform, Option<&mut Rad<f32>>, Entity)>,
+) {
+    let mut client = match client {
+        Some(client) => client,
+        None => return,
+    };
+    let player = client.player;
+    let mut states: HashMap<u64, EntityState> = client
+        .snapshots
+        .sample(time.seconds_since_startup() - INTERPOLATION_DELAY)
+        .into_iter()
+        .filter(|state| Some(state.id) != player)
+        .map(|state| (state.id, state))
+        .collect();
+
+    for (proxy, mut trans, angle, entity) in proxy_query.iter_mut() {
+        let state = match states.remove(&proxy.0) {
+            Some(state) => state,
+            None => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+
+        trans.translation.x = state.x;
+        trans.translation.y = state.y;
+        match angle {
+            Some(mut angle) => *angle = Rad(state.angle),
+            None => trans.rotation = Quat::from_rotation_z(state.angle),
+        }
+    }
+
+    for state in states.values() {
+        spawn_proxy(&mut commands, &sprite_sheets, &materials, state);
+    }
+}
+
+fn spawn_proxy(commands: &mut Commands, sprite_sheets: &SpriteSheets, materials: &crate::Materials, state: &EntityState) {
+    let character = match state.kind {
+        EntityKind::Player => "player",
+        EntityKind::Zom => "zom",
+        EntityKind::StrongZom => "strong_zom",
+        EntityKind::Boss => "boss",
+        EntityKind::Bullet => {
+            let mut transform = Transform::from_translation(state.location().extend(0.0));
+            transform.rotate(Quat::from_rotation_z(state.angle));
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite::new(Vec2::new(10.0, 4.0)),
+                    material: materials.bullet.clone(),
+                    transform,
+                    ..Default::default()
+                })
+                .insert(NetProxy(state.id));
+            return;
+        }
+    };
+
+    let z = match state.kind {
+        EntityKind::Player => 0.1,
+        _ => 0.0,
+    };
+    commands
+        .spawn_bundle(sprite_sheets.bundle(character, state.location().extend(z)))
+        .insert(CharacterAnimation::new(character))
+        .insert(Rad(state.angle))
+        .insert(NetProxy(state.id));
+}
+// -----------------------------------
+
+// LOCAL TEST
+// ----------------------------------
+/// A headless client that walks in circles and shoots, predicting its own movement the
+/// same way the game does and logging how far off the server says it was
+fn run_test_client(index: usize, server: SocketAddr) {
+    let socket = match bind(SocketAddr::from(([127, 0, 0, 1], 0))) {
+        Some(socket) => socket,
+        None => return,
+    };
+    let frame_time = Duration::from_secs_f32(1.0 / 60.0);
+    let started = Instant::now();
+    let mut last_hello: Option<Instant> = None;
+    let mut last_report = Instant::now();
+    let mut buffer = [0u8; MAX_PACKET];
+
+    let mut player = None;
+    let mut sequence = 0;
+    let mut location = Vec2::ZERO;
+    let mut prediction = Prediction::default();
+    let mut snapshots = 0;
+    let mut entities = 0;
+    let mut largest_correction = 0.0f32;
+
+    loop {
+        let hello_due = last_hello
+            .map(|last| last.elapsed().as_secs_f32() > HELLO_RETRY_SECONDS)
+            .unwrap_or(true);
+        if player.is_none() && hello_due {
+            send(&socket, server, &ClientMessage::Hello);
+            last_hello = Some(Instant::now());
+        }
+
+        while let Some((message, _)) = receive::<ServerMessage>(&socket, &mut buffer) {
+            match message {
+                ServerMessage::Welcome { player: id } => player = Some(id),
+                ServerMessage::Snapshot {
+                    last_input,
+                    part,
+                    entities: states,
+                    ..
+                } => {
+                    if part == 0 {
+                        snapshots += 1;
+                        entities = 0;
+                    }
+                    entities += states.len();
+                    if let Some(ours) = states.iter().find(|state| Some(state.id) == player) {
+                        let correction = prediction.reconcile(last_input, ours.location());
+                        location += correction;
+                        largest_correction = largest_correction.max(correction.length());
+                    }
+                }
+            }
+        }
+
+        if player.is_some() {
+            let t = started.elapsed().as_secs_f32() + index as f32;
+            let movement = Vec2::new(t.cos(), t.sin());
+            location += movement * crate::MOVE_SPEED;
+
+            sequence += 1;
+            prediction.record(sequence, location);
+            let actions = match t as u32 % 2 {
+                0 => vec![Action::Fire],
+                _ => Vec::new(),
+            };
+            let frame = InputFrame {
+                sequence,
+                movement: (movement.x, movement.y),
+                angle: t,
+                actions,
+            };
+            send(&socket, server, &ClientMessage::Input(frame));
+        }
+
+        if last_report.elapsed().as_secs() >= 5 {
+            info!(
+                "Test client {}: {} snapshots, {} entities, largest correction {:.1}",
+                index, snapshots, entities, largest_correction
+            );
+            last_report = Instant::now();
+            largest_correction = 0.0;
+        }
+
+        std::thread::sleep(frame_time);
+    }
+}
+// -----------------------------------
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn start_networking(mut commands: Commands, role: Res<NetRole>) {
+    match *role {
+        NetRole::Offline => {}
+        NetRole::Host(port) => {
+            if let Some(socket) = bind(SocketAddr::from(([0, 0, 0, 0], port))) {
+                info!("Hosting on port {}", port);
+                commands.insert_resource(NetServer::new(socket));
+            }
+        }
+        NetRole::Join(server) => {
+            if let Some(socket) = bind(SocketAddr::from(([0, 0, 0, 0], 0))) {
+                send(&socket, server, &ClientMessage::Hello);
+                commands.insert_resource(NetClient {
+                    socket,
+                    server,
+                    player: None,
+                    sequence: 0,
+                    hello_timer: Timer::from_seconds(HELLO_RETRY_SECONDS, true),
+                    snapshots: SnapshotBuffer::default(),
+                    prediction: Prediction::default(),
+                });
+            }
+        }
+        NetRole::LocalTest(clients) => {
+            let socket = match bind(SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT))) {
+                Some(socket) => socket,
+                None => return,
+            };
+            commands.insert_resource(NetServer::new(socket));
+
+            let server = SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT));
+            for index in 0..clients {
+                std::thread::spawn(move || run_test_client(index, server));
+            }
+            info!("Local test server started with {} clients", clients);
+        }
+    }
+}
+// -----------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(id: u64) -> EntityState {
+        EntityState {
+            id: u64::MAX - id,
+            kind: EntityKind::Player,
+            x: id as f32,
+            y: -(id as f32),
+            angle: 1.0,
+            health: 0.5,
+            ammo: Some(AmmoState {
+                loaded: u16::MAX,
+                reserve: u16::MAX,
+                reload_elapsed: Some(0.25),
+            }),
+        }
+    }
+
+    fn ids(entities: &[EntityState]) -> Vec<u64> {
+        entities.iter().map(|state| state.id).collect()
+    }
+
+    #[test]
+    fn split_snapshot_parts_fit_a_datagram_and_reassemble() {
+        let entities: Vec<EntityState> = (0..MAX_SNAPSHOT_ENTITIES as u64).map(state).collect();
+        let parts = split_snapshot(&entities);
+        assert!(parts.len() > 1);
+
+        let mut buffer = SnapshotBuffer::default();
+        for (index, part) in parts.iter().enumerate() {
+            let message = ServerMessage::Snapshot {
+                tick: u32::MAX,
+                last_input: u32::MAX,
+                part: index as u8,
+                parts: parts.len() as u8,
+                entities: part.to_vec(),
+            };
+            let bytes = encoding().serialize(&message).unwrap();
+            assert!(bytes.len() <= MAX_SNAPSHOT_BYTES, "part {} is {} bytes", index, bytes.len());
+
+            buffer.push_part(0.0, 1, index as u8, parts.len() as u8, part.to_vec());
+        }
+
+        assert_eq!(buffer.snapshots.len(), 1);
+        assert_eq!(ids(&buffer.snapshots[0].2), ids(&entities));
+    }
+
+    #[test]
+    fn split_snapshot_always_has_a_part() {
+        assert_eq!(split_snapshot(&[]).len(), 1);
+    }
+
+    #[test]
+    fn push_part_accepts_parts_out_of_order() {
+        let mut buffer = SnapshotBuffer::default();
+        buffer.push_part(0.0, 1, 2, 3, vec![state(2)]);
+        buffer.push_part(0.0, 1, 0, 3, vec![state(0)]);
+        assert!(buffer.snapshots.is_empty());
+
+        buffer.push_part(0.0, 1, 1, 3, vec![state(1)]);
+        assert_eq!(buffer.snapshots.len(), 1);
+        assert_eq!(buffer.snapshots[0].2.len(), 3);
+    }
+
+    #[test]
+    fn push_part_ignores_duplicates() {
+        let mut buffer = SnapshotBuffer::default();
+        buffer.push_part(0.0, 1, 0, 2, vec![state(0)]);
+        buffer.push_part(0.0, 1, 0, 2, vec![state(0)]);
+        assert!(buffer.snapshots.is_empty());
+
+        buffer.push_part(0.0, 1, 1, 2, vec![state(1)]);
+        assert_eq!(ids(&buffer.snapshots[0].2), ids(&[state(0), state(1)]));
+    }
+
+    #[test]
+    fn push_part_drops_incomplete_snapshots() {
+        let mut buffer = SnapshotBuffer::default();
+        buffer.push_part(0.0, 1, 0, 2, vec![state(0)]);
+        // Part 1 of tick 1 never arrives
+        buffer.push_part(0.1, 2, 0, 2, vec![state(2)]);
+        buffer.push_part(0.1, 2, 1, 2, vec![state(3)]);
+        // Too late, tick 2 has already replaced it
+        buffer.push_part(0.2, 1, 1, 2, vec![state(1)]);
+
+        assert_eq!(buffer.snapshots.len(), 1);
+        assert_eq!(buffer.snapshots[0].1, 2);
+        assert_eq!(ids(&buffer.snapshots[0].2), ids(&[state(2), state(3)]));
+    }
+
+    #[test]
+    fn push_part_rejects_bad_part_numbers() {
+        let mut buffer = SnapshotBuffer::default();
+        buffer.push_part(0.0, 1, 1, 1, vec![state(0)]);
+        buffer.push_part(0.0, 1, 0, 65, vec![state(0)]);
+
+        assert!(buffer.partial.is_none());
+        assert!(buffer.snapshots.is_empty());
+    }
+
+    #[test]
+    fn reconcile_moves_later_predictions_by_the_error() {
+        let mut prediction = Prediction::default();
+        prediction.record(1, Vec2::new(1.0, 0.0));
+        prediction.record(2, Vec2::new(2.0, 0.0));
+        prediction.record(3, Vec2::new(3.0, 0.0));
+
+        let error = prediction.reconcile(2, Vec2::new(2.0, 2.0));
+        assert_eq!(error, Vec2::new(0.0, 2.0));
+        assert_eq!(prediction.pending, VecDeque::from(vec![(3, Vec2::new(3.0, 2.0))]));
+
+        // The replayed prediction now agrees with the server
+        assert_eq!(prediction.reconcile(3, Vec2::new(3.0, 2.0)), Vec2::ZERO);
+        assert!(prediction.pending.is_empty());
+    }
+
+    #[test]
+    fn reconcile_ignores_small_errors() {
+        let mut prediction = Prediction::default();
+        prediction.record(1, Vec2::new(1.0, 0.0));
+        prediction.record(2, Vec2::new(2.0, 0.0));
+
+        let nudge = Vec2::new(PREDICTION_TOLERANCE / 2.0, 0.0);
+        assert_eq!(prediction.reconcile(1, Vec2::new(1.0, 0.0) + nudge), Vec2::ZERO);
+        assert_eq!(prediction.pending, VecDeque::from(vec![(2, Vec2::new(2.0, 0.0))]));
+    }
+
+    #[test]
+    fn reconcile_without_a_matching_prediction_changes_nothing() {
+        let mut prediction = Prediction::default();
+        prediction.record(5, Vec2::new(5.0, 0.0));
+
+        assert_eq!(prediction.reconcile(3, Vec2::new(100.0, 0.0)), Vec2::ZERO);
+        assert_eq!(prediction.pending, VecDeque::from(vec![(5, Vec2::new(5.0, 0.0))]));
+    }
+}
@@ -0,0 +1,285 @@
+use bevy::{
+    prelude::*,
+    render::texture::{Extent3d, TextureDimension, TextureFormat},
+};
+use rand::{self, Rng};
+
+use crate::events::{ShotFired, ZomHit};
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// Particles are recycled from a fixed pool, new ones are dropped once it runs dry
+static POOL_SIZE: usize = 512;
+static PARTICLE_Z: f32 = 0.3;
+
+#[derive(Clone, Copy)]
+pub enum ParticlePreset {
+    MuzzleFlash,
+    BloodSpray,
+    ShellCasing,
+    Explosion,
+}
+
+/// How a burst of particles looks and moves
+struct ParticleEffect {
+    count: (u32, u32),
+    lifetime: (f32, f32),
+    speed: (f32, f32),
+    /// Radians either side of the emit direction
+    spread: f32,
+    /// Fraction of velocity lost per second
+    drag: f32,
+    start_color: Color,
+    end_color: Color,
+    start_size: f32,
+    end_size: f32,
+}
+
+impl ParticlePreset {
+    fn effect(&self) -> ParticleEffect {
+        match self {
+            ParticlePreset::MuzzleFlash => ParticleEffect {
+                count: (4, 7),
+                lifetime: (0.05, 0.1),
+                speed: (100.0, 250.0),
+                spread: 0.3,
+                drag: 2.0,
+                start_color: Color::rgba(1.0, 0.95, 0.6, 1.0),
+                end_color: Color::rgba(1.0, 0.5, 0.0, 0.0),
+                start_size: 5.0,
+                end_size: 2.0,
+            },
+            ParticlePreset::BloodSpray => ParticleEffect {
+                count: (6, 12),
+                lifetime: (0.3, 0.5),
+                speed: (60.0, 180.0),
+                spread: 0.6,
+                drag: 4.0,
+                start_color: Color::rgba(0.7, 0.0, 0.0, 1.0),
+                end_color: Color::rgba(0.35, 0.0, 0.0, 0.0),
+                start_size: 3.0,
+                end_size: 1.5,
+            },
+            ParticlePreset::ShellCasing => ParticleEffect {
+                count: (1, 1),
+                lifetime: (0.6, 0.8),
+                speed: (60.0, 100.0),
+                spread: 0.4,
+                drag: 5.0,
+                start_color: Color::rgba(0.85, 0.65, 0.2, 1.0),
+                end_color: Color::rgba(0.6, 0.45, 0.15, 0.0),
+                start_size: 3.0,
+                end_size: 3.0,
+            },
+            ParticlePreset::Explosion => ParticleEffect {
+                count: (40, 60),
+                lifetime: (0.4, 0.9),
+                speed: (50.0, 350.0),
+                spread: PI,
+                drag: 3.0,
+                start_color: Color::rgba(1.0, 0.6, 0.1, 1.0),
+                end_color: Color::rgba(0.2, 0.2, 0.2, 0.0),
+                start_size: 6.0,
+                end_size: 14.0,
+            },
+        }
+    }
+}
+
+/// A one off burst of particles
+pub struct EmitParticles {
+    pub preset: ParticlePreset,
+    pub location: Vec2,
+    /// Radians, the centre of the effect's spread
+    pub direction: f32,
+}
+
+/// Continuously emits particles from the entity it is attached to
+pub struct ParticleEmitter {
+    preset: ParticlePreset,
+    bursts_per_second: f32,
+    accumulator: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(preset: ParticlePreset, bursts_per_second: f32) -> Self {
+        ParticleEmitter {
+            preset,
+            bursts_per_second,
+            accumulator: 0.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Particle {
+    preset: Option<ParticlePreset>,
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32,
+}
+
+/// Pooled particle entities that are not currently showing
+pub struct ParticlePool {
+    free: Vec<Entity>,
+}
+
+type ParticleQuery<'a> = (&'a mut Particle, &'a mut Transform, &'a mut Visible);
+
+fn burst(
+    pool: &mut ParticlePool,
+    particle_query: &mut Query<ParticleQuery, Without<ParticleEmitter>>,
+    preset: ParticlePreset,
+    location: Vec2,
+    direction: f32,
+) {
+    let effect = preset.effect();
+    let mut random = rand::thread_rng();
+
+    for _index in 0..random.gen_range(effect.count.0..=effect.count.1) {
+        let entity = match pool.free.pop() {
+            Some(entity) => entity,
+            None => return,
+        };
+
+        if let Ok((mut particle, mut trans, mut visible)) = particle_query.get_mut(entity) {
+            let angle = direction + random.gen_range(-effect.spread..=effect.spread);
+            let speed = random.gen_range(effect.speed.0..=effect.speed.1);
+
+            particle.preset = Some(preset);
+            particle.velocity = Vec2::new(angle.cos(), angle.sin()) * speed;
+            particle.age = 0.0;
+            particle.lifetime = random.gen_range(effect.lifetime.0..=effect.lifetime.1);
+
+            trans.translation = location.extend(PARTICLE_Z);
+            visible.is_visible = true;
+        }
+    }
+}
+
+pub fn emit_particles(
+    time: Res<Time>,
+    mut pool: ResMut<ParticlePool>,
+    mut emit_reader: EventReader<EmitParticles>,
+    mut emitter_query: Query<(&mut ParticleEmitter, &Transform)>,
+    mut particle_query: Query<ParticleQuery, Without<ParticleEmitter>>,
+) {
+    for emit in emit_reader.iter() {
+        burst(&mut pool, &mut particle_query, emit.preset, emit.location, emit.direction);
+    }
+
+    let mut random = rand::thread_rng();
+    for (mut emitter, trans) in emitter_query.iter_mut() {
+        emitter.accumulator += emitter.bursts_per_second * time.delta_seconds();
+
+        while emitter.accumulator >= 1.0 {
+            emitter.accumulator -= 1.0;
+
+            let direction = random.gen_range(-PI..PI);
+            burst(&mut pool, &mut particle_query, emitter.preset, trans.translation.truncate(), direction);
+        }
+    }
+}
+
+pub fn update_particles(
+    time: Res<Time>,
+    mut pool: ResMut<ParticlePool>,
+    mut particle_query: Query<(&mut Particle, &mut Transform, &mut Visible, &mut TextureAtlasSprite, Entity)>,
+) {
+    let delta = time.delta_seconds();
+
+    for (mut particle, mut trans, mut visible, mut sprite, entity) in particle_query.iter_mut() {
+        let effect = match particle.preset {
+            Some(preset) => preset.effect(),
+            None => continue,
+        };
+
+        particle.age += delta;
+        if particle.age >= particle.lifetime {
+            particle.preset = None;
+            visible.is_visible = false;
+            pool.free.push(entity);
+            continue;
+        }
+
+        let drag = (1.0 - effect.drag * delta).max(0.0);
+        particle.velocity *= drag;
+        trans.translation += (particle.velocity * delta).extend(0.0);
+
+        let life = particle.age / particle.lifetime;
+        let lerp = |start: f32, end: f32| start + (end - start) * life;
+
+        sprite.color = Color::rgba(
+            lerp(effect.start_color.r(), effect.end_color.r()),
+            lerp(effect.start_color.g(), effect.end_color.g()),
+            lerp(effect.start_color.b(), effect.end_color.b()),
+            lerp(effect.start_color.a(), effect.end_color.a()),
+        );
+
+        let size = lerp(effect.start_size, effect.end_size);
+        trans.scale = Vec3::new(size, size, 1.0);
+    }
+}
+
+pub fn combat_particles(
+    mut shot_reader: EventReader<ShotFired>,
+    mut hit_reader: EventReader<ZomHit>,
+    mut particle_writer: EventWriter<EmitParticles>,
+) {
+    for shot in shot_reader.iter() {
+        particle_writer.send(EmitParticles {
+            preset: ParticlePreset::MuzzleFlash,
+            location: shot.origin,
+            direction: shot.angle.0,
+        });
+        // Casings fly out to the right of the barrel
+        particle_writer.send(EmitParticles {
+            preset: ParticlePreset::ShellCasing,
+            location: shot.origin,
+            direction: shot.angle.0 - FRAC_PI_2,
+        });
+    }
+
+    for hit in hit_reader.iter() {
+        particle_writer.send(EmitParticles {
+            preset: ParticlePreset::BloodSpray,
+            location: hit.location,
+            direction: hit.direction.y.atan2(hit.direction.x),
+        });
+    }
+}
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn load_particle_pool(
+    mut commands: Commands,
+    mut textures: ResMut<Assets<Texture>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let white = textures.add(Texture::new_fill(
+        Extent3d::new(1, 1, 1),
+        TextureDimension::D2,
+        &[255, 255, 255, 255],
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+    let atlas = texture_atlases.add(TextureAtlas::from_grid(white, Vec2::new(1.0, 1.0), 1, 1));
+
+    let free = (0..POOL_SIZE)
+        .map(|_| {
+            commands
+                .spawn_bundle(SpriteSheetBundle {
+                    texture_atlas: atlas.clone(),
+                    visible: Visible {
+                        is_visible: false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(Particle::default())
+                .id()
+        })
+        .collect();
+
+    commands.insert_resource(ParticlePool { free });
+}
+// -----------------------------------
@@ -0,0 +1,184 @@
+use bevy::prelude::*;
+use cgmath::Rad;
+use rand::{self, Rng};
+
+use crate::events::ShotFired;
+use crate::wall::{self, Wall};
+use crate::coop::Downed;
+use crate::{Player, Velocity, Zom, ZomType};
+
+use std::f32::consts::PI;
+
+/// Anything closer than this is noticed regardless of facing or walls
+static TOUCH_RADIUS: f32 = 40.0;
+static WANDER_RADIUS: f32 = 150.0;
+static WANDER_SECONDS: f32 = 3.0;
+static WANDER_SPEED_FACTOR: f32 = 0.35;
+static ARRIVED_DISTANCE: f32 = 8.0;
+
+/// A sound made in the world that nearby zombies can hear
+#[derive(Clone, Copy)]
+pub struct Noise {
+    pub origin: Vec2,
+    pub radius: f32,
+}
+
+enum Awareness {
+    Wandering { target: Option<Vec2>, timer: Timer },
+    Investigating(Vec2),
+    Chasing(Vec2),
+}
+
+pub struct Perception {
+    sight_radius: f32,
+    /// Half angle of the view cone
+    sight_cone: Rad<f32>,
+    awareness: Awareness,
+}
+
+impl Perception {
+    pub fn for_zom_type(zom_type: &ZomType) -> Self {
+        let (sight_radius, sight_cone) = match zom_type {
+            ZomType::Default => (250.0, Rad(PI / 3.0)),
+            ZomType::Strong => (200.0, Rad(PI / 4.0)),
+            ZomType::Boss => (600.0, Rad(PI)),
+        };
+
+        Perception {
+            sight_radius,
+            sight_cone,
+            awareness: Awareness::Wandering {
+                target: None,
+                timer: Timer::from_seconds(WANDER_SECONDS, true),
+            },
+        }
+    }
+
+    /// Where the zombie is currently heading, if anywhere
+    pub fn target(&self) -> Option<Vec2> {
+        match self.awareness {
+            Awareness::Wandering { target, .. } => target,
+            Awareness::Investigating(location) => Some(location),
+            Awareness::Chasing(location) => Some(location),
+        }
+    }
+
+    pub fn speed_factor(&self) -> f32 {
+        match self.awareness {
+            Awareness::Wandering { .. } => WANDER_SPEED_FACTOR,
+            _ => 1.0,
+        }
+    }
+
+    fn can_see(
+        &self,
+        location: Vec2,
+        facing: Rad<f32>,
+        player_location: Vec2,
+        wall_query: &Query<(&Wall, &Transform)>,
+    ) -> bool {
+        let dist = Velocity::between_transforms(&location, &player_location).magnitude();
+        if dist < TOUCH_RADIUS {
+            return true;
+        }
+        if dist > self.sight_radius {
+            return false;
+        }
+
+        let angle_to_player = location.get_angle_to(&player_location);
+        let diff = (angle_to_player.0 - facing.0 + PI).rem_euclid(PI * 2.0) - PI;
+
+        diff.abs() <= self.sight_cone.0 && wall::line_of_sight(wall_query, location, player_location)
+    }
+}
+
+fn nearest<'a>(location: Vec2, locations: impl Iterator<Item = &'a Vec2>) -> Option<&'a Vec2> {
+    locations.min_by(|a, b| {
+        let a = Velocity::between_transforms(&location, a).magnitude();
+        let b = Velocity::between_transforms(&location, b).magnitude();
+        a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+    })
+}
+
+pub fn hear_shots(mut shot_reader: EventReader<ShotFired>, mut noise_writer: EventWriter<Noise>) {
+    for shot in shot_reader.iter() {
+        noise_writer.send(Noise {
+            origin: shot.origin,
+            radius: shot.loudness,
+        });
+    }
+}
+
+pub fn update_perception(
+    time: Res<Time>,
+    mut noise_reader: EventReader<Noise>,
+    wall_query: Query<(&Wall, &Transform)>,
+    player_query: Query<&Transform, (With<Player>, Without<Downed>)>,
+    mut zom_query: Query<(&mut Perception, &Transform, &Rad<f32>), With<Zom>>,
+) {
+    let player_locations: Vec<Vec2> = player_query.iter().map(|trans| trans.translation.truncate()).collect();
+    if player_locations.is_empty() {
+        return;
+    }
+    let noises: Vec<Noise> = noise_reader.iter().cloned().collect();
+    let mut random = rand::thread_rng();
+
+    for (mut perception, trans, facing) in zom_query.iter_mut() {
+        let location = trans.translation.truncate();
+
+        // Chase the nearest living player in sight
+        let seen = nearest(
+            location,
+            player_locations
+                .iter()
+                .filter(|player_location| perception.can_see(location, *facing, **player_location, &wall_query)),
+        );
+        if let Some(player_location) = seen {
+            perception.awareness = Awareness::Chasing(*player_location);
+            continue;
+        }
+
+        // Lost sight, head to where the player was last seen
+        if let Awareness::Chasing(last_seen) = perception.awareness {
+            perception.awareness = Awareness::Investigating(last_seen);
+        }
+
+        let heard = noises.iter().find(|noise| {
+            Velocity::between_transforms(&location, &noise.origin).magnitude() <= noise.radius
+        });
+        if let Some(noise) = heard {
+            perception.awareness = Awareness::Investigating(noise.origin);
+            continue;
+        }
+
+        match &mut perception.awareness {
+            Awareness::Investigating(target) => {
+                if Velocity::between_transforms(&location, target).magnitude() < ARRIVED_DISTANCE {
+                    perception.awareness = Awareness::Wandering {
+                        target: None,
+                        timer: Timer::from_seconds(WANDER_SECONDS, true),
+                    };
+                }
+            }
+            Awareness::Wandering { target, timer } => {
+                if let Some(wander_target) = *target {
+                    if Velocity::between_transforms(&location, &wander_target).magnitude() < ARRIVED_DISTANCE {
+                        *target = None;
+                    }
+                }
+
+                // Out of range of everything, so roam to a random spot nearby
+                if timer.tick(time.delta()).just_finished() {
+                    *target = Some(
+                        location
+                            + Vec2::new(
+                                random.gen_range(-WANDER_RADIUS..WANDER_RADIUS),
+                                random.gen_range(-WANDER_RADIUS..WANDER_RADIUS),
+                            ),
+                    );
+                }
+            }
+            Awareness::Chasing(_) => {}
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::{self, Rng};
+
+use crate::coop::Downed;
+use crate::events::ZomKilled;
+use crate::{Health, Materials, Player, ZomType};
+
+static PICKUP_SIZE: f32 = 12.0;
+/// How close a player has to walk to pick something up
+static PICKUP_RADIUS: f32 = 20.0;
+static PICKUP_SECONDS: f32 = 15.0;
+/// Pickups blink for this long before they vanish
+static BLINK_SECONDS: f32 = 4.0;
+static BLINKS_PER_SECOND: f32 = 4.0;
+/// How far apart pickups dropped together land
+static SCATTER: f32 = 25.0;
+static MEDKIT_HEAL: f32 = 40.0;
+static AMMO_BOX_MAGAZINES: u16 = 2;
+static DAMAGE_BOOST: f32 = 2.0;
+static DAMAGE_BOOST_SECONDS: f32 = 10.0;
+static SPEED_BOOST: f32 = 1.4;
+static SPEED_BOOST_SECONDS: f32 = 8.0;
+static INVULNERABILITY_SECONDS: f32 = 5.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PickupKind {
+    Medkit,
+    AmmoBox,
+    DamageBoost,
+    SpeedBoost,
+    Invulnerability,
+}
+
+impl PickupKind {
+    pub const ALL: [PickupKind; 5] = [
+        PickupKind::Medkit,
+        PickupKind::AmmoBox,
+        PickupKind::DamageBoost,
+        PickupKind::SpeedBoost,
+        PickupKind::Invulnerability,
+    ];
+
+    pub fn color(&self) -> Color {
+        match self {
+            PickupKind::Medkit => Color::rgb(0.95, 0.95, 0.95),
+            PickupKind::AmmoBox => Color::rgb(0.6, 0.5, 0.2),
+            PickupKind::DamageBoost => Color::rgb(0.9, 0.3, 0.2),
+            PickupKind::SpeedBoost => Color::rgb(0.2, 0.7, 0.9),
+            PickupKind::Invulnerability => Color::rgb(0.7, 0.4, 0.9),
+        }
+    }
+
+    /// The timed buff this gives and for how many seconds, if it isn't used up straight away
+    fn buff(&self) -> Option<(Buff, f32)> {
+        match self {
+            PickupKind::DamageBoost => Some((Buff::Damage, DAMAGE_BOOST_SECONDS)),
+            PickupKind::SpeedBoost => Some((Buff::Speed, SPEED_BOOST_SECONDS)),
+            PickupKind::Invulnerability => Some((Buff::Invulnerable, INVULNERABILITY_SECONDS)),
+            PickupKind::Medkit | PickupKind::AmmoBox => None,
+        }
+    }
+}
+
+/// Something lying on the ground until a player walks over it or it times out
+pub struct Pickup {
+    kind: PickupKind,
+    lifetime: Timer,
+}
+
+/// Odds of what a zombie leaves behind when it dies
+struct DropTable {
+    /// Chance of each roll dropping anything
+    chance: f64,
+    rolls: u32,
+    weights: &'static [(PickupKind, u32)],
+}
+
+impl DropTable {
+    fn for_zom_type(zom_type: ZomType) -> Self {
+        match zom_type {
+            ZomType::Default => DropTable {
+                chance: 0.1,
+                rolls: 1,
+                weights: &[
+                    (PickupKind::Medkit, 3),
+                    (PickupKind::AmmoBox, 4),
+                    (PickupKind::DamageBoost, 1),
+                    (PickupKind::SpeedBoost, 1),
+                ],
+            },
+            ZomType::Strong => DropTable {
+                chance: 0.3,
+                rolls: 1,
+                weights: &[
+                    (PickupKind::Medkit, 3),
+                    (PickupKind::AmmoBox, 3),
+                    (PickupKind::DamageBoost, 2),
+                    (PickupKind::SpeedBoost, 2),
+                    (PickupKind::Invulnerability, 1),
+                ],
+            },
+            ZomType::Boss => DropTable {
+                chance: 1.0,
+                rolls: 3,
+                weights: &[
+                    (PickupKind::AmmoBox, 2),
+                    (PickupKind::DamageBoost, 1),
+                    (PickupKind::SpeedBoost, 1),
+                    (PickupKind::Invulnerability, 1),
+                ],
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Buff {
+    Damage,
+    Speed,
+    Invulnerable,
+}
+
+impl Buff {
+    /// Every buff, in the order the HUD shows them
+    pub const ALL: [Buff; 3] = [Buff::Damage, Buff::Speed, Buff::Invulnerable];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Buff::Damage => "DMG",
+            Buff::Speed => "SPD",
+            Buff::Invulnerable => "INV",
+        }
+    }
+
+    /// Same colour as the pickup that gives it
+    pub fn color(&self) -> Color {
+        match self {
+            Buff::Damage => PickupKind::DamageBoost.color(),
+            Buff::Speed => PickupKind::SpeedBoost.color(),
+            Buff::Invulnerable => PickupKind::Invulnerability.color(),
+        }
+    }
+}
+
+/// A player's running timed buffs
+#[derive(Default)]
+pub struct Buffs(Vec<(Buff, Timer)>);
+
+impl Buffs {
+    /// Starts `buff`, or restarts it if it's already running
+    fn add(&mut self, buff: Buff, seconds: f32) {
+        self.0.retain(|(active, _)| *active != buff);
+        self.0.push((buff, Timer::from_seconds(seconds, false)));
+    }
+
+    pub fn active(&self, buff: Buff) -> bool {
+        self.0.iter().any(|(active, _)| *active == buff)
+    }
+
+    pub fn seconds_left(&self, buff: Buff) -> Option<f32> {
+        self.0
+            .iter()
+            .find(|(active, _)| *active == buff)
+            .map(|(_, timer)| timer.duration().as_secs_f32() - timer.elapsed_secs())
+    }
+
+    pub fn damage_factor(&self) -> f32 {
+        match self.active(Buff::Damage) {
+            true => DAMAGE_BOOST,
+            false => 1.0,
+        }
+    }
+
+    pub fn speed_factor(&self) -> f32 {
+        match self.active(Buff::Speed) {
+            true => SPEED_BOOST,
+            false => 1.0,
+        }
+    }
+}
+
+/// Rolls each dead zombie's drop table
+pub fn drop_pickups(mut commands: Commands, materials: Res<Materials>, mut killed_reader: EventReader<ZomKilled>) {
+    let mut random = rand::thread_rng();
+
+    for killed in killed_reader.iter() {
+        let table = DropTable::for_zom_type(killed.zom_type);
+        for _ in 0..table.rolls {
+            if !random.gen_bool(table.chance) {
+                continue;
+            }
+            let kind = match table.weights.choose_weighted(&mut random, |(_, weight)| *weight) {
+                Ok((kind, _)) => *kind,
+                Err(_) => continue,
+            };
+
+            // Spread out several drops so they don't stack on the same spot
+            let offset = match table.rolls {
+                1 => Vec2::ZERO,
+                _ => Vec2::new(random.gen_range(-SCATTER..SCATTER), random.gen_range(-SCATTER..SCATTER)),
+            };
+            let location = killed.location + offset;
+
+            commands
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite::new(Vec2::splat(PICKUP_SIZE)),
+                    material: materials.pickup[&kind].clone(),
+                    transform: Transform::from_xyz(location.x, location.y, 0.05),
+                    ..Default::default()
+                })
+                .insert(Pickup {
+                    kind,
+                    lifetime: Timer::from_seconds(PICKUP_SECONDS, false),
+                });
+        }
+    }
+}
+
+/// Blinks pickups that are about to vanish, and removes them once they have
+pub fn expire_pickups(mut commands: Commands, time: Res<Time>, mut pickup_query: Query<(&mut Pickup, &mut Visible, Entity)>) {
+    for (mut pickup, mut visible, entity) in pickup_query.iter_mut() {
+        pickup.lifetime.tick(time.delta());
+        if pickup.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let left = PICKUP_SECONDS - pickup.lifetime.elapsed_secs();
+        visible.is_visible = left > BLINK_SECONDS || (left * BLINKS_PER_SECOND * 2.0) as u32 % 2 == 0;
+    }
+}
+
+pub fn collect_pickups(
+    mut commands: Commands,
+    pickup_query: Query<(&Pickup, &Transform, Entity)>,
+    mut player_query: Query<(&mut Player, &mut Health, &mut Buffs, &Transform), Without<Downed>>,
+) {
+    // Two players can reach the same pickup in one frame, only the first gets it
+    let mut collected = Vec::new();
+
+    for (mut player, mut health, mut buffs, player_trans) in player_query.iter_mut() {
+        let player_location = player_trans.translation.truncate();
+
+        for (pickup, pickup_trans, entity) in pickup_query.iter() {
+            if collected.contains(&entity)
+                || (pickup_trans.translation.truncate() - player_location).length() > PICKUP_RADIUS
+            {
+                continue;
+            }
+
+            match pickup.kind {
+                // Left for a teammate who needs it
+                PickupKind::Medkit if health.current >= health.max => continue,
+                PickupKind::Medkit => health.heal(MEDKIT_HEAL),
+                PickupKind::AmmoBox => {
+                    let player = &mut *player;
+                    for gun in player.gun.iter_mut().chain(player.spare_guns.iter_mut()) {
+                        gun.add_ammo(AMMO_BOX_MAGAZINES);
+                    }
+                }
+                kind => {
+                    if let Some((buff, seconds)) = kind.buff() {
+                        buffs.add(buff, seconds);
+                    }
+                }
+            }
+
+            collected.push(entity);
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+pub fn update_buffs(time: Res<Time>, mut buffs_query: Query<&mut Buffs>) {
+    for mut buffs in buffs_query.iter_mut() {
+        for (_, timer) in buffs.0.iter_mut() {
+            timer.tick(time.delta());
+        }
+        buffs.0.retain(|(_, timer)| !timer.finished());
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{ZomHit, ZomKilled};
+use crate::settings;
+use crate::wave::Wave;
+use crate::{Health, Player, ZomType};
+
+/// Kills closer together than this keep the combo going
+static COMBO_SECONDS: f32 = 2.0;
+static MAX_MULTIPLIER: u32 = 8;
+static HIGH_SCORE_COUNT: usize = 10;
+static HIGH_SCORE_FILE: &str = "high_scores.ron";
+
+pub struct Score {
+    pub points: u32,
+    /// Kills in the current combo
+    pub combo: u32,
+    combo_timer: Timer,
+    /// Damage dealt to normal and strong zombies this run
+    damage_dealt: f32,
+    boss_damage: f32,
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Score {
+            points: 0,
+            combo: 0,
+            combo_timer: Timer::from_seconds(COMBO_SECONDS, false),
+            damage_dealt: 0.0,
+            boss_damage: 0.0,
+        }
+    }
+}
+
+impl Score {
+    pub fn multiplier(&self) -> u32 {
+        self.combo.max(1).min(MAX_MULTIPLIER)
+    }
+
+    fn points_for(zom_type: ZomType) -> u32 {
+        match zom_type {
+            ZomType::Default => 10,
+            ZomType::Strong => 30,
+            ZomType::Boss => 500,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HighScore {
+    pub points: u32,
+    pub wave: u32,
+}
+
+/// Best runs, highest first, kept in the user's data directory
+#[derive(Serialize, Deserialize, Default)]
+pub struct HighScores {
+    entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    /// Adds a run to the table, returns its place if it made the cut
+    fn record(&mut self, score: HighScore) -> Option<usize> {
+        let place = self
+            .entries
+            .iter()
+            .position(|entry| score.points > entry.points)
+            .unwrap_or_else(|| self.entries.len());
+        if place >= HIGH_SCORE_COUNT {
+            return None;
+        }
+
+        self.entries.insert(place, score);
+        self.entries.truncate(HIGH_SCORE_COUNT);
+        Some(place)
+    }
+}
+
+pub struct GameOverScreen;
+
+pub fn score_kills(time: Res<Time>, mut score: ResMut<Score>, mut killed_reader: EventReader<ZomKilled>) {
+    let score = &mut *score;
+
+    if score.combo_timer.tick(time.delta()).finished() {
+        score.combo = 0;
+    }
+
+    for killed in killed_reader.iter() {
+        score.combo += 1;
+        score.combo_timer.reset();
+        score.points += Score::points_for(killed.zom_type) * score.multiplier();
+    }
+}
+
+pub fn tally_damage(mut score: ResMut<Score>, mut hit_reader: EventReader<ZomHit>) {
+    for hit in hit_reader.iter() {
+        match hit.zom_type {
+            ZomType::Boss => score.boss_damage += hit.damage,
+            _ => score.damage_dealt += hit.damage,
+        }
+    }
+}
+
+/// Ends the run once every player is down and shows the high score table
+pub fn game_over(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    score: Res<Score>,
+    wave: Res<Wave>,
+    mut high_scores: ResMut<HighScores>,
+    player_query: Query<(&Health, Entity), With<Player>>,
+) {
+    if player_query.iter().next().is_none() || player_query.iter().any(|(health, _)| !health.is_dead()) {
+        return;
+    }
+    for (_, player) in player_query.iter() {
+        commands.entity(player).despawn_recursive();
+    }
+
+    let place = high_scores.record(HighScore {
+        points: score.points,
+        wave: wave.number,
+    });
+    settings::save_data_file(HIGH_SCORE_FILE, &*high_scores);
+
+    let mut lines = vec![
+        "GAME OVER".to_string(),
+        format!("Score: {}  Wave: {}", score.points, wave.number),
+        format!("Damage: {:.0}  Boss damage: {:.0}", score.damage_dealt, score.boss_damage),
+        String::new(),
+        "High Scores".to_string(),
+    ];
+    for (index, entry) in high_scores.entries.iter().enumerate() {
+        let marker = if Some(index) == place { ">" } else { " " };
+        lines.push(format!("{}{:>2}. {:>7}  wave {}", marker, index + 1, entry.points, entry.wave));
+    }
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(ColorMaterial::color(Color::rgba(0.0, 0.0, 0.0, 0.7))),
+            ..Default::default()
+        })
+        .insert(GameOverScreen)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    lines.join("\n"),
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 28.0,
+                        color: Color::WHITE,
+                    },
+                    TextAlignment::default(),
+                ),
+                ..Default::default()
+            });
+        });
+}
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn load_high_scores(mut commands: Commands) {
+    commands.insert_resource(settings::load_data_file::<HighScores>(HIGH_SCORE_FILE));
+}
+// -----------------------------------
@@ -0,0 +1,323 @@
+use bevy::asset::FileAssetIo;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::{Action, Binding, Bindings};
+use crate::GameState;
+
+use std::path::PathBuf;
+
+static SETTINGS_FILE: &str = "settings.ron";
+static FONT_PATH: &str = "fonts/FiraMono-Medium.ttf";
+
+/// A file in the game's folder in the user's data directory
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("zom_pew_pew").join(name))
+}
+
+/// Writes `value` as RON to `name` in the user's data directory
+pub fn save_data_file<T: Serialize>(name: &str, value: &T) {
+    let path = match data_file(name) {
+        Some(path) => path,
+        None => return,
+    };
+    let contents = match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::new()) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warn!("Failed to serialise {}: {}", name, err);
+            return;
+        }
+    };
+
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Err(err) = std::fs::write(&path, contents) {
+        warn!("Failed to save {}: {}", path.display(), err);
+    }
+}
+
+/// Reads `name` from the user's data directory, falling back to the default
+pub fn load_data_file<T: for<'de> Deserialize<'de> + Default>(name: &str) -> T {
+    data_file(name)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| ron::de::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// A file in the game's assets folder, found from the same root as the asset server
+pub fn asset_file(name: &str) -> PathBuf {
+    FileAssetIo::get_root_path().join("assets").join(name)
+}
+
+/// Reads RON from `name` in the assets folder, falling back to `bundled`, the copy built into the game
+pub fn load_asset_file<T: for<'de> Deserialize<'de>>(name: &str, bundled: &str) -> T {
+    let path = asset_file(name);
+    let loaded = std::fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| ron::de::from_str(&contents).map_err(|err| err.to_string()));
+
+    match loaded {
+        Ok(value) => value,
+        Err(err) => {
+            warn!("Failed to load {}, using the built in copy: {}", path.display(), err);
+            ron::de::from_str(bundled).unwrap_or_else(|err| panic!("Built in {} is invalid: {}", name, err))
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum CrosshairStyle {
+    /// The image from `sights/default.png`
+    Sight,
+    Cross,
+    Dot,
+}
+
+impl CrosshairStyle {
+    fn next(&self) -> Self {
+        match self {
+            CrosshairStyle::Sight => CrosshairStyle::Cross,
+            CrosshairStyle::Cross => CrosshairStyle::Dot,
+            CrosshairStyle::Dot => CrosshairStyle::Sight,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            CrosshairStyle::Sight => "Sight",
+            CrosshairStyle::Cross => "Cross",
+            CrosshairStyle::Dot => "Dot",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub crosshair: CrosshairStyle,
+    /// Shake the camera when firing
+    pub camera_kick: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            crosshair: CrosshairStyle::Sight,
+            camera_kick: true,
+        }
+    }
+}
+
+pub struct SettingsMenu;
+
+#[derive(Clone, Copy)]
+pub enum SettingsButton {
+    Crosshair,
+    CameraKick,
+    Bind(Action),
+    Resume,
+}
+
+/// The action waiting for a new key or button, if any
+#[derive(Default)]
+pub struct Rebinding(Option<Action>);
+
+/// Text showing the current value of a setting
+pub struct SettingValue(SettingsButton);
+
+pub fn toggle_settings(
+    input: Res<Input<KeyCode>>,
+    mut state: ResMut<State<GameState>>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    if !input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    // Escape cancels a rebind rather than closing the menu
+    if rebinding.0.is_some() {
+        rebinding.0 = None;
+        return;
+    }
+
+    let next = match state.current() {
+        GameState::Playing => GameState::Settings,
+        GameState::Settings => GameState::Playing,
+        // The shop is left with its own button
+        GameState::Shop => return,
+    };
+    let _ = state.set(next);
+}
+
+pub fn settings_buttons(
+    mut settings: ResMut<Settings>,
+    bindings: Res<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut state: ResMut<State<GameState>>,
+    button_query: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    mut value_query: Query<(&SettingValue, &mut Text)>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            SettingsButton::Crosshair => {
+                settings.crosshair = settings.crosshair.next();
+                save_data_file(SETTINGS_FILE, &*settings);
+            }
+            SettingsButton::CameraKick => {
+                settings.camera_kick = !settings.camera_kick;
+                save_data_file(SETTINGS_FILE, &*settings);
+            }
+            SettingsButton::Bind(action) => {
+                rebinding.0 = Some(*action);
+            }
+            SettingsButton::Resume => {
+                let _ = state.set(GameState::Playing);
+            }
+        }
+    }
+
+    for (value, mut text) in value_query.iter_mut() {
+        text.sections[0].value = match value.0 {
+            SettingsButton::Crosshair => format!("Crosshair: {}", settings.crosshair.name()),
+            SettingsButton::CameraKick => match settings.camera_kick {
+                true => "Camera kick: On".to_string(),
+                false => "Camera kick: Off".to_string(),
+            },
+            SettingsButton::Bind(action) => match (rebinding.0, bindings.get(action)) {
+                (Some(waiting), _) if waiting == action => format!("{}: press a key...", action.name()),
+                (_, Some(binding)) => format!("{}: {}", action.name(), binding.name()),
+                (_, None) => format!("{}: -", action.name()),
+            },
+            SettingsButton::Resume => "Resume".to_string(),
+        };
+    }
+}
+
+/// Binds the next key or mouse button pressed after picking an action
+pub fn capture_rebinding(
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+) {
+    // Skip the frame the button was clicked so the click isn't bound
+    if rebinding.is_changed() {
+        return;
+    }
+    let action = match rebinding.0 {
+        Some(action) => action,
+        None => return,
+    };
+
+    let pressed = keys
+        .get_just_pressed()
+        .find(|key| **key != KeyCode::Escape)
+        .map(|key| Binding::Key(*key))
+        .or_else(|| mouse.get_just_pressed().next().map(|button| Binding::Mouse(*button)));
+
+    if let Some(binding) = pressed {
+        bindings.rebind(action, binding);
+        rebinding.0 = None;
+    }
+}
+
+pub fn open_settings_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut windows: ResMut<Windows>,
+) {
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_cursor_visibility(true);
+    }
+
+    let button_material = materials.add(Color::rgb(0.2, 0.2, 0.2).into());
+    let text_style = TextStyle {
+        font: asset_server.load(FONT_PATH),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.7).into()),
+            ..Default::default()
+        })
+        .insert(SettingsMenu)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section("SETTINGS", text_style.clone(), TextAlignment::default()),
+                ..Default::default()
+            });
+
+            let mut buttons = vec![SettingsButton::Crosshair, SettingsButton::CameraKick];
+            buttons.extend(Action::ALL.iter().map(|action| SettingsButton::Bind(*action)));
+            buttons.push(SettingsButton::Resume);
+
+            for button in buttons.iter() {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(320.0), Val::Px(30.0)),
+                            margin: Rect::all(Val::Px(3.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        material: button_material.clone(),
+                        ..Default::default()
+                    })
+                    .insert(*button)
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                text: Text::with_section("", text_style.clone(), TextAlignment::default()),
+                                ..Default::default()
+                            })
+                            .insert(SettingValue(*button));
+                    });
+            }
+        });
+}
+
+pub fn close_settings_menu(
+    mut commands: Commands,
+    mut windows: ResMut<Windows>,
+    mut rebinding: ResMut<Rebinding>,
+    menu_query: Query<Entity, With<SettingsMenu>>,
+) {
+    rebinding.0 = None;
+
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_cursor_visibility(false);
+    }
+
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn load_settings(mut commands: Commands, mut windows: ResMut<Windows>) {
+    // The crosshair replaces the system cursor while playing
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_cursor_visibility(false);
+    }
+
+    commands.insert_resource(load_data_file::<Settings>(SETTINGS_FILE));
+    commands.insert_resource(Rebinding::default());
+}
+// -----------------------------------
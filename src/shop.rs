@@ -0,0 +1,367 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::events::ZomKilled;
+use crate::gun::{self, Gun, GunStats};
+use crate::settings::load_asset_file;
+use crate::stamina::Stamina;
+use crate::{GameState, Health, Player, ZomType};
+
+static SHOP_FILE: &str = "shop.ron";
+static BUNDLED_SHOP: &str = include_str!("../assets/shop.ron");
+static FONT_PATH: &str = "fonts/FiraMono-Medium.ttf";
+static AMMO_MAGAZINES: u16 = 3;
+static ARMOUR_PER_PURCHASE: f32 = 50.0;
+static MAX_ARMOUR: f32 = 100.0;
+static HEALTH_UPGRADE: f32 = 25.0;
+static STAMINA_UPGRADE: f32 = 25.0;
+static GRENADES_PER_PURCHASE: u32 = 2;
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum ShopWeapon {
+    Smg,
+    AssaultRifle,
+    Ak47,
+}
+
+impl ShopWeapon {
+    fn stats(&self) -> &'static GunStats {
+        match self {
+            ShopWeapon::Smg => &gun::SMG,
+            ShopWeapon::AssaultRifle => &gun::ASSAULT_RIFLE,
+            ShopWeapon::Ak47 => &gun::AK_47,
+        }
+    }
+
+    fn build(&self) -> Box<dyn Gun> {
+        gun::Firearm::new(self.stats())
+    }
+
+    fn name(&self) -> &'static str {
+        self.stats().name
+    }
+
+    fn owned_by(&self, player: &Player) -> bool {
+        let name = self.name();
+        player
+            .gun
+            .iter()
+            .chain(player.spare_guns.iter())
+            .any(|gun| gun.name() == name)
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum Upgrade {
+    MaxHealth,
+    MaxStamina,
+    Grenades,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum ShopItem {
+    Weapon(ShopWeapon),
+    /// Spare magazines for every gun the player carries
+    Ammo,
+    Armour,
+    Upgrade(Upgrade),
+}
+
+impl ShopItem {
+    fn name(&self) -> String {
+        match self {
+            ShopItem::Weapon(weapon) => weapon.name().to_string(),
+            ShopItem::Ammo => "Ammo".to_string(),
+            ShopItem::Armour => "Armour".to_string(),
+            ShopItem::Upgrade(Upgrade::MaxHealth) => "Max health".to_string(),
+            ShopItem::Upgrade(Upgrade::MaxStamina) => "Max stamina".to_string(),
+            ShopItem::Upgrade(Upgrade::Grenades) => "Grenades".to_string(),
+        }
+    }
+
+    /// Why this can't be bought for `player` right now, if it can't
+    fn unavailable(&self, player: &Player, armour: &Armour) -> Option<&str> {
+        match self {
+            ShopItem::Weapon(weapon) if weapon.owned_by(player) => Some("owned"),
+            ShopItem::Armour if armour.0 >= MAX_ARMOUR => Some("full"),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, player: &mut Player, health: &mut Health, stamina: &mut Stamina, armour: &mut Armour) {
+        match self {
+            ShopItem::Weapon(weapon) => {
+                // The new gun goes straight into the player's hands
+                if let Some(held) = player.gun.replace(weapon.build()) {
+                    player.spare_guns.push(held);
+                }
+            }
+            ShopItem::Ammo => {
+                for gun in player.gun.iter_mut().chain(player.spare_guns.iter_mut()) {
+                    gun.add_ammo(AMMO_MAGAZINES);
+                }
+            }
+            ShopItem::Armour => armour.0 = (armour.0 + ARMOUR_PER_PURCHASE).min(MAX_ARMOUR),
+            ShopItem::Upgrade(Upgrade::MaxHealth) => {
+                health.max += HEALTH_UPGRADE;
+                health.heal(HEALTH_UPGRADE);
+            }
+            ShopItem::Upgrade(Upgrade::MaxStamina) => stamina.raise_max(STAMINA_UPGRADE),
+            ShopItem::Upgrade(Upgrade::Grenades) => player.grenades += GRENADES_PER_PURCHASE,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct KillRewards {
+    default: u32,
+    strong: u32,
+    boss: u32,
+}
+
+#[derive(Deserialize)]
+struct Listing {
+    item: ShopItem,
+    price: u32,
+}
+
+/// Shop prices and kill rewards, from `assets/shop.ron`
+#[derive(Deserialize)]
+pub struct ShopPrices {
+    kill_rewards: KillRewards,
+    items: Vec<Listing>,
+}
+
+impl ShopPrices {
+    fn reward_for(&self, zom_type: ZomType) -> u32 {
+        match zom_type {
+            ZomType::Default => self.kill_rewards.default,
+            ZomType::Strong => self.kill_rewards.strong,
+            ZomType::Boss => self.kill_rewards.boss,
+        }
+    }
+}
+
+/// Money the whole team has to spend in the shop
+#[derive(Default)]
+pub struct Cash(pub u32);
+
+/// Soaks up damage before health does, bought in the shop
+#[derive(Default)]
+pub struct Armour(f32);
+
+impl Armour {
+    /// Takes as much of `amount` as it can, returning what's left over for health
+    pub fn absorb(&mut self, amount: f32) -> f32 {
+        let absorbed = amount.min(self.0);
+        self.0 -= absorbed;
+        amount - absorbed
+    }
+
+    pub fn points(&self) -> f32 {
+        self.0
+    }
+}
+
+pub struct ShopMenu;
+
+#[derive(Clone, Copy)]
+pub enum ShopButton {
+    Buyer,
+    /// Index into the shop's listings
+    Buy(usize),
+    NextWave,
+}
+
+pub struct ShopLabel(ShopButton);
+
+pub struct ShopCashText;
+
+/// Number of the player purchases go to
+#[derive(Default)]
+pub struct ShopBuyer(usize);
+
+pub fn earn_cash(prices: Res<ShopPrices>, mut cash: ResMut<Cash>, mut killed_reader: EventReader<ZomKilled>) {
+    for killed in killed_reader.iter() {
+        cash.0 += prices.reward_for(killed.zom_type);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn shop_buttons(
+    mut cash: ResMut<Cash>,
+    prices: Res<ShopPrices>,
+    mut buyer: ResMut<ShopBuyer>,
+    mut state: ResMut<State<GameState>>,
+    button_query: Query<(&Interaction, &ShopButton), Changed<Interaction>>,
+    mut player_query: Query<(&mut Player, &mut Health, &mut Stamina, &mut Armour)>,
+    mut label_query: Query<(&ShopLabel, &mut Text), Without<ShopCashText>>,
+    mut cash_query: Query<&mut Text, With<ShopCashText>>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button {
+            ShopButton::Buyer => {
+                let mut numbers: Vec<usize> = player_query.iter_mut().map(|(player, ..)| player.number).collect();
+                numbers.sort_unstable();
+                buyer.0 = numbers
+                    .iter()
+                    .find(|number| **number > buyer.0)
+                    .or_else(|| numbers.first())
+                    .copied()
+                    .unwrap_or(0);
+            }
+            ShopButton::Buy(index) => {
+                let listing = &prices.items[*index];
+                if cash.0 < listing.price {
+                    continue;
+                }
+
+                let customer = player_query
+                    .iter_mut()
+                    .find(|(player, ..)| player.number == buyer.0);
+                if let Some((mut player, mut health, mut stamina, mut armour)) = customer {
+                    if listing.item.unavailable(&player, &armour).is_none() {
+                        listing.item.apply(&mut player, &mut health, &mut stamina, &mut armour);
+                        cash.0 -= listing.price;
+                    }
+                }
+            }
+            ShopButton::NextWave => {
+                let _ = state.set(GameState::Playing);
+            }
+        }
+    }
+
+    if let Ok(mut text) = cash_query.single_mut() {
+        text.sections[0].value = format!("Cash: ${}", cash.0);
+    }
+
+    let customer = player_query.iter_mut().find(|(player, ..)| player.number == buyer.0);
+    let customer = customer.as_ref().map(|(player, _, _, armour)| (&**player, &**armour));
+    for (label, mut text) in label_query.iter_mut() {
+        let section = &mut text.sections[0];
+        match label.0 {
+            ShopButton::Buyer => section.value = format!("Buying for: P{}", buyer.0 + 1),
+            ShopButton::Buy(index) => {
+                let listing = &prices.items[index];
+                let unavailable = customer.and_then(|(player, armour)| listing.item.unavailable(player, armour));
+
+                section.value = match unavailable {
+                    Some(reason) => format!("{}  ({})", listing.item.name(), reason),
+                    None => format!("{}  ${}", listing.item.name(), listing.price),
+                };
+                // Greyed out when it can't be bought
+                section.style.color = match unavailable.is_none() && cash.0 >= listing.price {
+                    true => Color::WHITE,
+                    false => Color::GRAY,
+                };
+            }
+            ShopButton::NextWave => section.value = "Next wave".to_string(),
+        }
+    }
+}
+
+pub fn open_shop(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut windows: ResMut<Windows>,
+    prices: Res<ShopPrices>,
+    mut buyer: ResMut<ShopBuyer>,
+) {
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_cursor_visibility(true);
+    }
+    buyer.0 = 0;
+
+    let button_material = materials.add(Color::rgb(0.2, 0.2, 0.2).into());
+    let text_style = TextStyle {
+        font: asset_server.load(FONT_PATH),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.7).into()),
+            ..Default::default()
+        })
+        .insert(ShopMenu)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section("SHOP", text_style.clone(), TextAlignment::default()),
+                ..Default::default()
+            });
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section("", text_style.clone(), TextAlignment::default()),
+                    ..Default::default()
+                })
+                .insert(ShopCashText);
+
+            let mut buttons = vec![ShopButton::Buyer];
+            buttons.extend((0..prices.items.len()).map(ShopButton::Buy));
+            buttons.push(ShopButton::NextWave);
+
+            for button in buttons.iter() {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(320.0), Val::Px(30.0)),
+                            margin: Rect::all(Val::Px(3.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        material: button_material.clone(),
+                        ..Default::default()
+                    })
+                    .insert(*button)
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                text: Text::with_section("", text_style.clone(), TextAlignment::default()),
+                                ..Default::default()
+                            })
+                            .insert(ShopLabel(*button));
+                    });
+            }
+        });
+}
+
+pub fn close_shop(
+    mut commands: Commands,
+    mut windows: ResMut<Windows>,
+    menu_query: Query<Entity, With<ShopMenu>>,
+) {
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_cursor_visibility(false);
+    }
+
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn load_shop(mut commands: Commands) {
+    let prices: ShopPrices = load_asset_file(SHOP_FILE, BUNDLED_SHOP);
+
+    commands.insert_resource(prices);
+    commands.insert_resource(Cash::default());
+    commands.insert_resource(ShopBuyer::default());
+}
+// -----------------------------------
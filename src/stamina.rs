@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+pub static PLAYER_STAMINA: f32 = 100.0;
+/// Stamina regained each second once the player stops spending it
+static REGEN_PER_SECOND: f32 = 30.0;
+static REGEN_DELAY_SECONDS: f32 = 0.8;
+static DASH_SECONDS: f32 = 0.18;
+
+pub struct Stamina {
+    current: f32,
+    max: f32,
+    /// Time since stamina was last spent, regen waits for it to finish
+    regen_delay: Timer,
+}
+
+impl Stamina {
+    pub fn new(max: f32) -> Self {
+        Stamina {
+            current: max,
+            max,
+            regen_delay: Timer::from_seconds(REGEN_DELAY_SECONDS, false),
+        }
+    }
+
+    /// Takes `amount` if there is enough of it
+    pub fn spend(&mut self, amount: f32) -> bool {
+        if self.current < amount {
+            return false;
+        }
+
+        self.current -= amount;
+        self.regen_delay.reset();
+        true
+    }
+
+    /// Takes as much of `amount` as is left, for costs paid over time
+    pub fn drain(&mut self, amount: f32) -> bool {
+        if self.current <= 0.0 {
+            return false;
+        }
+
+        self.current = (self.current - amount).max(0.0);
+        self.regen_delay.reset();
+        true
+    }
+
+    pub fn regen(&mut self, delta: std::time::Duration) {
+        self.regen_delay.tick(delta);
+        if self.regen_delay.finished() {
+            self.current = (self.current + REGEN_PER_SECOND * delta.as_secs_f32()).min(self.max);
+        }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.current / self.max
+    }
+
+    /// Raises the cap and fills the new room straight away
+    pub fn raise_max(&mut self, amount: f32) {
+        self.max += amount;
+        self.current += amount;
+    }
+}
+
+/// A quick burst of movement that can't be hurt
+pub struct Dash {
+    timer: Timer,
+    direction: Vec2,
+}
+
+impl Default for Dash {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(DASH_SECONDS, false);
+        timer.tick(timer.duration());
+
+        Dash {
+            timer,
+            direction: Vec2::ZERO,
+        }
+    }
+}
+
+impl Dash {
+    pub fn start(&mut self, direction: Vec2) {
+        self.timer.reset();
+        self.direction = direction;
+    }
+
+    /// Direction to keep moving in while the dash lasts
+    pub fn tick(&mut self, delta: std::time::Duration) -> Option<Vec2> {
+        self.timer.tick(delta);
+        match self.timer.finished() {
+            true => None,
+            false => Some(self.direction),
+        }
+    }
+
+    pub fn invulnerable(&self) -> bool {
+        !self.timer.finished()
+    }
+}
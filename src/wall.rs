@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::settings::load_asset_file;
+
+static WALLS_FILE: &str = "walls.ron";
+static BUNDLED_WALLS: &str = include_str!("../assets/walls.ron");
+/// Radius used when pushing people out of walls
+static BODY_RADIUS: f32 = 12.0;
+
+pub struct Wall {
+    half_size: Vec2,
+}
+
+/// Where a wall goes in `walls.ron`, centred on `x` and `y`
+#[derive(Deserialize)]
+struct WallPlacement {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl Wall {
+    pub fn new(size: Vec2) -> Self {
+        Wall { half_size: size / 2.0 }
+    }
+
+    /// Where along `from -> to` (0.0 to 1.0) the segment enters this wall,
+    /// along with the normal of the face it hits
+    pub fn segment_hit(&self, center: Vec2, from: Vec2, to: Vec2) -> Option<(f32, Vec2)> {
+        let min = center - self.half_size;
+        let max = center + self.half_size;
+        let delta = to - from;
+
+        let mut t_enter = 0.0f32;
+        let mut t_exit = 1.0f32;
+        let mut normal = Vec2::ZERO;
+
+        for axis in 0..2 {
+            let (start, dir, low, high) = match axis {
+                0 => (from.x, delta.x, min.x, max.x),
+                _ => (from.y, delta.y, min.y, max.y),
+            };
+
+            if dir.abs() < f32::EPSILON {
+                if start < low || start > high {
+                    return None;
+                }
+                continue;
+            }
+
+            let mut t_low = (low - start) / dir;
+            let mut t_high = (high - start) / dir;
+            let mut face = -1.0;
+            if t_low > t_high {
+                std::mem::swap(&mut t_low, &mut t_high);
+                face = 1.0;
+            }
+
+            if t_low > t_enter {
+                t_enter = t_low;
+                normal = match axis {
+                    0 => Vec2::new(face, 0.0),
+                    _ => Vec2::new(0.0, face),
+                };
+            }
+            t_exit = t_exit.min(t_high);
+
+            if t_enter > t_exit {
+                return None;
+            }
+        }
+
+        Some((t_enter, normal))
+    }
+
+    fn push_out(&self, center: Vec2, point: Vec2, radius: f32) -> Option<Vec2> {
+        let closest = point.max(center - self.half_size).min(center + self.half_size);
+        let offset = point - closest;
+        let dist = offset.length();
+
+        if dist >= radius {
+            return None;
+        }
+
+        if dist > f32::EPSILON {
+            return Some(offset / dist * (radius - dist));
+        }
+
+        // Centre is inside the wall, push out along the shallowest axis
+        let inside = point - center;
+        let depth = self.half_size - inside.abs();
+        if depth.x < depth.y {
+            Some(Vec2::new((depth.x + radius) * inside.x.signum(), 0.0))
+        } else {
+            Some(Vec2::new(0.0, (depth.y + radius) * inside.y.signum()))
+        }
+    }
+}
+
+pub fn line_of_sight(wall_query: &Query<(&Wall, &Transform)>, from: Vec2, to: Vec2) -> bool {
+    wall_query
+        .iter()
+        .all(|(wall, trans)| wall.segment_hit(trans.translation.truncate(), from, to).is_none())
+}
+
+/// The closest wall the segment passes into, as a fraction along it and the wall's normal
+pub fn first_hit(wall_query: &Query<(&Wall, &Transform)>, from: Vec2, to: Vec2) -> Option<(f32, Vec2)> {
+    wall_query
+        .iter()
+        .filter_map(|(wall, trans)| wall.segment_hit(trans.translation.truncate(), from, to))
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+pub fn collide_walls(
+    wall_query: Query<(&Wall, &Transform)>,
+    mut body_query: Query<&mut Transform, (Or<(With<crate::Player>, With<crate::Zom>)>, Without<Wall>)>,
+) {
+    for mut trans in body_query.iter_mut() {
+        for (wall, wall_trans) in wall_query.iter() {
+            let center = wall_trans.translation.truncate();
+            if let Some(push) = wall.push_out(center, trans.translation.truncate(), BODY_RADIUS) {
+                trans.translation += push.extend(0.0);
+            }
+        }
+    }
+}
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn load_walls(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let layout: Vec<WallPlacement> = load_asset_file(WALLS_FILE, BUNDLED_WALLS);
+    let material = materials.add(Color::DARK_GRAY.into());
+
+    for placement in layout.iter() {
+        let size = Vec2::new(placement.width, placement.height);
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite::new(size),
+                material: material.clone(),
+                transform: Transform::from_xyz(placement.x, placement.y, 0.02),
+                ..Default::default()
+            })
+            .insert(Wall::new(size));
+    }
+}
+// -----------------------------------
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::settings::load_asset_file;
+use crate::GameState;
+
+static WAVES_FILE: &str = "waves.ron";
+static BUNDLED_WAVES: &str = include_str!("../assets/waves.ron");
+
+#[derive(Deserialize)]
+pub struct WaveConfig {
+    /// Waves on which a boss joins the horde
+    pub boss_waves: Vec<u32>,
+    pub first_wave_size: u32,
+    pub wave_growth: u32,
+    pub break_seconds: f32,
+}
+
+pub struct Wave {
+    pub number: u32,
+    /// Zombies still to be spawned this wave
+    pub remaining: u32,
+    pub boss_pending: bool,
+    break_timer: Timer,
+    /// Whether the shop has been opened during this wave's break
+    shop_opened: bool,
+}
+
+impl Wave {
+    pub fn new(config: &WaveConfig) -> Self {
+        Wave {
+            number: 1,
+            remaining: config.first_wave_size,
+            boss_pending: config.boss_waves.contains(&1),
+            break_timer: Timer::from_seconds(config.break_seconds, false),
+            shop_opened: false,
+        }
+    }
+
+    fn next(&mut self, config: &WaveConfig) {
+        self.number += 1;
+        self.remaining = config.first_wave_size + config.wave_growth * (self.number - 1);
+        self.boss_pending = config.boss_waves.contains(&self.number);
+        self.break_timer = Timer::from_seconds(config.break_seconds, false);
+        self.shop_opened = false;
+    }
+}
+
+pub fn advance_wave(
+    mut wave: ResMut<Wave>,
+    config: Res<WaveConfig>,
+    time: Res<Time>,
+    mut state: ResMut<State<GameState>>,
+    zom_query: Query<&crate::Zom>,
+) {
+    if wave.remaining > 0 || wave.boss_pending || zom_query.iter().next().is_some() {
+        return;
+    }
+
+    // The break starts with a trip to the shop
+    if !wave.shop_opened {
+        wave.shop_opened = true;
+        let _ = state.set(GameState::Shop);
+        return;
+    }
+
+    if wave.break_timer.tick(time.delta()).finished() {
+        wave.next(&config);
+    }
+}
+
+// SETUP FUNCTIONS
+// ----------------------------------
+pub fn load_waves(mut commands: Commands) {
+    let config: WaveConfig = load_asset_file(WAVES_FILE, BUNDLED_WAVES);
+
+    commands.insert_resource(Wave::new(&config));
+    commands.insert_resource(config);
+}
+// -----------------------------------